@@ -0,0 +1,269 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
+    text::{Line, Span},
+    Frame,
+};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use crate::arch::operations::ArchOperation;
+use crate::config;
+use crate::disk_usage::{self, MountUsage};
+use crate::filesystem::{dir_size, format_size, home_dir, xdg_dir};
+use crate::trash;
+use crate::theme;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+const MIB: u64 = 1024 * 1024;
+
+// Filesystems fuller than this get a recommendation of their own
+const FULL_MOUNT_RATIO: f64 = 0.9;
+
+// What the surrounding TUI should do after a key press
+pub enum DashboardEvent {
+    None,
+    Quit,
+    Continue,
+    Apply(Vec<String>), // Check the operations with these ids, then continue
+}
+
+// Something the tool knows how to shrink, or at least knows takes a lot of space
+struct Consumer {
+    name: &'static str,
+    bytes: u64,
+    operation: Option<&'static str>, // The operation that cleans it up
+    threshold: u64,                  // Size above which cleaning it up is recommended
+}
+
+struct Recommendation {
+    text: String,
+    operation: Option<&'static str>,
+}
+
+pub struct Dashboard {
+    mounts: Vec<MountUsage>,
+    consumers: Option<Vec<Consumer>>, // None while they are being measured
+    receiver: Option<Receiver<Vec<Consumer>>>,
+}
+
+impl Dashboard {
+    pub fn new() -> Dashboard {
+        Dashboard { mounts: Vec::new(), consumers: None, receiver: None }
+    }
+
+    // Filesystem usage is cheap to read, the consumers are measured in the background
+    pub fn refresh(&mut self) {
+        self.mounts = disk_usage::mount_usage();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(measure_consumers());
+        });
+        self.consumers = None;
+        self.receiver = Some(receiver);
+    }
+
+    pub fn is_measuring(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    // Picks up the sizes once they are measured
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else { return };
+        match receiver.try_recv() {
+            Ok(consumers) => self.consumers = Some(consumers),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => self.consumers = Some(Vec::new()),
+        }
+        self.receiver = None;
+    }
+
+    fn recommendations(&self) -> Vec<Recommendation> {
+        let mut recommendations: Vec<Recommendation> = self.mounts
+            .iter()
+            .filter(|mount| mount.used_ratio() >= FULL_MOUNT_RATIO)
+            .map(|mount| Recommendation {
+                text: format!(
+                    "{} is {:.0}% full, {} left",
+                    mount.mount_point.display(),
+                    mount.used_ratio() * 100.0,
+                    format_size(mount.available)
+                ),
+                operation: None,
+            })
+            .collect();
+        for consumer in self.consumers.iter().flatten().filter(|consumer| consumer.bytes > consumer.threshold) {
+            let text = match consumer.operation.and_then(ArchOperation::from_id) {
+                Some(operation) => format!("{}: {} uses {}", operation.name(), consumer.name, format_size(consumer.bytes)),
+                None if consumer.name == "Containers" => format!(
+                    "Containers use {}, `docker system prune` or `podman system prune` removes unused images",
+                    format_size(consumer.bytes)
+                ),
+                None => format!("{} uses {}", consumer.name, format_size(consumer.bytes)),
+            };
+            recommendations.push(Recommendation { text, operation: consumer.operation });
+        }
+        recommendations
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> DashboardEvent {
+        match key {
+            KeyCode::Char('q') => DashboardEvent::Quit,
+            KeyCode::Enter | KeyCode::Esc => DashboardEvent::Continue,
+            KeyCode::Char('r') => {
+                self.refresh();
+                DashboardEvent::None
+            }
+            KeyCode::Char('a') if self.consumers.is_some() => {
+                let ids = self.recommendations().iter().filter_map(|r| r.operation).map(String::from).collect();
+                DashboardEvent::Apply(ids)
+            }
+            _ => DashboardEvent::None,
+        }
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let mounts_height = self.mounts.len().max(1) as u16 + 2;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(mounts_height),
+                Constraint::Min(5),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let theme = theme::get();
+
+        // One gauge per filesystem, labelled with its mount point
+        let mounts_block = Block::default().borders(Borders::ALL).title("Filesystems");
+        let inner = mounts_block.inner(chunks[0]);
+        f.render_widget(mounts_block, chunks[0]);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1); self.mounts.len()])
+            .split(inner);
+        for (mount, row) in self.mounts.iter().zip(rows.iter()) {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+                .split(*row);
+            f.render_widget(Paragraph::new(format!("{} ({})", mount.mount_point.display(), mount.fs_type)), columns[0]);
+            let ratio = mount.used_ratio().clamp(0.0, 1.0);
+            // Nearly full filesystems are marked with a ! too, for when the colour can't be told apart
+            let marker = if ratio >= FULL_MOUNT_RATIO { "! " } else { "" };
+            let label = format!("{}{} of {} used ({:.0}%)", marker, format_size(mount.used), format_size(mount.total), ratio * 100.0);
+            let style = if ratio >= FULL_MOUNT_RATIO { theme.error } else if ratio >= 0.75 { theme.warning } else { theme.ok };
+            if theme.colors {
+                let gauge = Gauge::default()
+                    .gauge_style(style.bg(theme.track))
+                    .ratio(ratio)
+                    .label(label);
+                f.render_widget(gauge, columns[1]);
+            } else {
+                // Gauges are drawn with colours only, so draw the bar in text
+                let width = (columns[1].width as usize).saturating_sub(label.len() + 3);
+                let filled = (ratio * width as f64).round() as usize;
+                let bar = format!("[{}{}] {}", "#".repeat(filled), "-".repeat(width - filled), label);
+                f.render_widget(Paragraph::new(bar).style(style), columns[1]);
+            }
+        }
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(45),
+                Constraint::Percentage(55),
+            ].as_ref())
+            .split(chunks[1]);
+
+        let consumers: Vec<ListItem> = match &self.consumers {
+            Some(consumers) => consumers
+                .iter()
+                .map(|consumer| {
+                    let (marker, style) = if consumer.bytes > consumer.threshold { ("!", theme.warning) } else { (" ", Style::default()) };
+                    ListItem::new(format!("{} {:>10}  {}", marker, format_size(consumer.bytes), consumer.name)).style(style)
+                })
+                .collect(),
+            None => vec![ListItem::new("Measuring...")],
+        };
+        let consumers = List::new(consumers).block(Block::default().borders(Borders::ALL).title("Top space consumers"));
+        f.render_widget(consumers, body[0]);
+
+        let recommendations: Vec<ListItem> = match &self.consumers {
+            Some(_) => {
+                let recommendations = self.recommendations();
+                if recommendations.is_empty() {
+                    vec![ListItem::new(Span::styled("Nothing needs cleaning up", theme::get().ok))]
+                } else {
+                    recommendations
+                        .into_iter()
+                        .map(|r| {
+                            let marker = if r.operation.is_some() { "[a] " } else { "    " };
+                            ListItem::new(Line::from(vec![
+                                Span::styled(marker, theme::get().key),
+                                Span::raw(r.text),
+                            ]))
+                        })
+                        .collect()
+                }
+            }
+            None => vec![ListItem::new("Measuring...")],
+        };
+        let recommendations = List::new(recommendations).block(Block::default().borders(Borders::ALL).title("Recommended actions"));
+        f.render_widget(recommendations, body[1]);
+
+        let footer = Paragraph::new("Enter: choose operations  a: check the [a] recommendations  r: refresh  q: quit")
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+    }
+}
+
+// Sizes of the places the tool knows about, largest first. Directories that can't be read count as empty.
+fn measure_consumers() -> Vec<Consumer> {
+    let home = |path: &str| home_dir().map(|home| home.join(path));
+    let size = |paths: &[Option<PathBuf>]| paths.iter().flatten().map(|path| dir_size(path)).sum();
+    let mut consumers = vec![
+        Consumer {
+            name: "Package cache",
+            bytes: size(&[Some(PathBuf::from("/var/cache/pacman/pkg"))]),
+            operation: Some("clean-package-cache"),
+            threshold: 2 * GIB,
+        },
+        Consumer {
+            name: "Systemd journal",
+            bytes: size(&[Some(PathBuf::from("/var/log/journal"))]),
+            operation: Some("clear-systemd-journal"),
+            threshold: 500 * MIB,
+        },
+        Consumer {
+            name: "Flatpak",
+            bytes: size(&[Some(PathBuf::from("/var/lib/flatpak")), home(".local/share/flatpak")]),
+            operation: Some("remove-unused-flatpak"),
+            threshold: 10 * GIB,
+        },
+        Consumer {
+            name: "User cache",
+            bytes: size(&[xdg_dir("XDG_CACHE_HOME", ".cache")]),
+            operation: Some("clean-user-cache"),
+            threshold: GIB,
+        },
+        Consumer {
+            name: "Trash",
+            bytes: trash::summarize(config::get().trash_min_age_days).total_bytes,
+            operation: Some("empty-trash"),
+            threshold: GIB,
+        },
+        Consumer {
+            name: "Containers",
+            bytes: size(&[Some(PathBuf::from("/var/lib/docker")), Some(PathBuf::from("/var/lib/containers")), home(".local/share/containers")]),
+            operation: None,
+            threshold: 20 * GIB,
+        },
+    ];
+    consumers.sort_by_key(|consumer| std::cmp::Reverse(consumer.bytes));
+    consumers
+}
@@ -0,0 +1,274 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    text::{Line, Span},
+    Frame,
+};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use crate::arch::file_finder::FinderEvent;
+use crate::arch::operations::ArchOperation;
+use crate::duplicates::{self, DedupAction, DuplicateGroup, DuplicateResolution};
+use crate::filesystem::{format_size, home_dir};
+use crate::theme;
+
+enum Stage {
+    EnteringPaths,
+    Scanning(Receiver<Vec<DuplicateGroup>>),
+    Browsing,
+}
+
+struct GroupChoice {
+    group: DuplicateGroup,
+    keep: usize,
+    action: Option<DedupAction>,
+}
+
+// A row of the flattened list: either a group header or one of its files
+enum Row {
+    Group(usize),
+    File(usize, usize),
+}
+
+pub struct DuplicateFinder {
+    paths_input: String,
+    stage: Stage,
+    groups: Vec<GroupChoice>,
+    state: ListState,
+}
+
+impl DuplicateFinder {
+    pub fn new() -> DuplicateFinder {
+        DuplicateFinder {
+            paths_input: home_dir().map(|home| home.display().to_string()).unwrap_or_default(),
+            stage: Stage::EnteringPaths,
+            groups: Vec::new(),
+            state: ListState::default(),
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        matches!(self.stage, Stage::Scanning(_))
+    }
+
+    pub fn has_marks(&self) -> bool {
+        self.groups.iter().any(|choice| choice.action.is_some())
+    }
+
+    pub fn poll_scan(&mut self) {
+        let groups = match &self.stage {
+            Stage::Scanning(receiver) => match receiver.try_recv() {
+                Ok(groups) => groups,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Vec::new(),
+            },
+            _ => return,
+        };
+        self.groups = groups.into_iter().map(|group| GroupChoice { group, keep: 0, action: None }).collect();
+        self.state.select(Some(0));
+        self.stage = Stage::Browsing;
+    }
+
+    // Directories are separated by ':' like $PATH
+    fn start_scan(&mut self) {
+        let roots: Vec<PathBuf> = self.paths_input.split(':').map(str::trim).filter(|p| !p.is_empty()).map(PathBuf::from).collect();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(duplicates::find_duplicates(&roots));
+        });
+        self.stage = Stage::Scanning(receiver);
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (group_index, choice) in self.groups.iter().enumerate() {
+            rows.push(Row::Group(group_index));
+            rows.extend((0..choice.group.paths.len()).map(|file_index| Row::File(group_index, file_index)));
+        }
+        rows
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.rows().into_iter().nth(self.state.selected()?)
+    }
+
+    fn set_action(&mut self, action: DedupAction) {
+        let group_index = match self.selected_row() {
+            Some(Row::Group(g)) | Some(Row::File(g, _)) => g,
+            None => return,
+        };
+        let choice = &mut self.groups[group_index];
+        choice.action = if choice.action == Some(action) { None } else { Some(action) };
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> FinderEvent {
+        match self.stage {
+            Stage::EnteringPaths => match key {
+                KeyCode::Esc => return FinderEvent::Exit,
+                KeyCode::Enter if !self.paths_input.trim().is_empty() => self.start_scan(),
+                KeyCode::Backspace => {
+                    self.paths_input.pop();
+                }
+                KeyCode::Char(c) => self.paths_input.push(c),
+                _ => {}
+            },
+            Stage::Scanning(_) => {
+                if key == KeyCode::Esc {
+                    self.stage = Stage::EnteringPaths;
+                }
+            }
+            Stage::Browsing => {
+                let len = self.rows().len();
+                match key {
+                    KeyCode::Esc | KeyCode::Char('q') => return FinderEvent::Exit,
+                    KeyCode::Char('c') if self.has_marks() => return FinderEvent::Confirm,
+                    KeyCode::Char('d') => self.set_action(DedupAction::Delete),
+                    KeyCode::Char('h') => self.set_action(DedupAction::Hardlink),
+                    KeyCode::Char('s') => self.stage = Stage::EnteringPaths,
+                    KeyCode::Enter => {
+                        if let Some(Row::File(group_index, file_index)) = self.selected_row() {
+                            self.groups[group_index].keep = file_index;
+                        }
+                    }
+                    KeyCode::Down if len > 0 => {
+                        let i = self.state.selected().map(|i| (i + 1) % len).unwrap_or(0);
+                        self.state.select(Some(i));
+                    }
+                    KeyCode::Up if len > 0 => {
+                        let i = self.state.selected().map(|i| if i == 0 { len - 1 } else { i - 1 }).unwrap_or(0);
+                        self.state.select(Some(i));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        FinderEvent::None
+    }
+
+    fn resolutions(&self) -> Vec<DuplicateResolution> {
+        self.groups
+            .iter()
+            .filter_map(|choice| {
+                let action = choice.action?;
+                let keep = choice.group.paths[choice.keep].clone();
+                let duplicates = choice.group.paths.iter().filter(|path| **path != keep).cloned().collect();
+                Some(DuplicateResolution { keep, duplicates, action, size: choice.group.size })
+            })
+            .collect()
+    }
+
+    pub fn operations(&self) -> Vec<ArchOperation> {
+        let resolutions = self.resolutions();
+        if resolutions.is_empty() {
+            Vec::new()
+        } else {
+            vec![ArchOperation::DeduplicateFiles(resolutions)]
+        }
+    }
+
+    pub fn confirmation_lines(&self) -> Vec<String> {
+        self.resolutions()
+            .iter()
+            .map(|resolution| {
+                let verb = match resolution.action {
+                    DedupAction::Delete => "delete",
+                    DedupAction::Hardlink => "hardlink",
+                };
+                format!(
+                    "Keep {}, {} {} copies ({})",
+                    resolution.keep.display(),
+                    verb,
+                    resolution.duplicates.len(),
+                    format_size(resolution.size * resolution.duplicates.len() as u64)
+                )
+            })
+            .collect()
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let wasted: u64 = self.groups.iter().map(|c| c.group.size * (c.group.paths.len() as u64 - 1)).sum();
+        let title = match self.stage {
+            Stage::EnteringPaths => String::from("Enter the directories to search, separated by ':'"),
+            Stage::Scanning(_) => format!("Searching {}...", self.paths_input.trim()),
+            Stage::Browsing => format!("{} groups of duplicates, {} reclaimable", self.groups.len(), format_size(wasted)),
+        };
+        let header = Paragraph::new(title)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL).title("Duplicate file finder"));
+        f.render_widget(header, chunks[0]);
+
+        match self.stage {
+            Stage::EnteringPaths => {
+                let input = Paragraph::new(Line::from(vec![
+                    Span::raw("Directories: "),
+                    Span::styled(format!("{}_", self.paths_input), theme::get().input),
+                ]))
+                .block(Block::default().borders(Borders::ALL));
+                f.render_widget(input, chunks[1]);
+            }
+            Stage::Scanning(_) => {
+                let waiting = Paragraph::new("Comparing files, this can take a while for large directories...")
+                    .block(Block::default().borders(Borders::ALL));
+                f.render_widget(waiting, chunks[1]);
+            }
+            Stage::Browsing => self.draw_groups(f, chunks[1]),
+        }
+
+        let help = match self.stage {
+            Stage::EnteringPaths => "Enter: search  Esc: back",
+            Stage::Scanning(_) => "Esc: cancel",
+            Stage::Browsing => "Enter: keep this copy  d: delete others  h: hardlink others  s: new search  c: confirm  Esc: back",
+        };
+        let footer = Paragraph::new(help)
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+    }
+
+    fn draw_groups<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let items: Vec<ListItem> = self.rows()
+            .into_iter()
+            .map(|row| match row {
+                Row::Group(g) => {
+                    let choice = &self.groups[g];
+                    let action = match choice.action {
+                        Some(DedupAction::Delete) => " [delete others]",
+                        Some(DedupAction::Hardlink) => " [hardlink others]",
+                        None => "",
+                    };
+                    ListItem::new(format!("{} copies of {}{}", choice.group.paths.len(), format_size(choice.group.size), action))
+                        .style(theme::get().heading)
+                }
+                Row::File(g, i) => {
+                    let choice = &self.groups[g];
+                    let (marker, style) = if i == choice.keep {
+                        ("keep", theme::get().ok)
+                    } else if choice.action.is_some() {
+                        ("    ", theme::get().error)
+                    } else {
+                        ("    ", Style::default())
+                    };
+                    ListItem::new(format!("  {} {}", marker, choice.group.paths[i].display())).style(style)
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL))
+            .highlight_style(theme::get().highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut self.state.clone());
+    }
+}
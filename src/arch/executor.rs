@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use crate::arch::operations::ArchOperation;
+use crate::arch::pacman_lock::{self, LockState};
+use crate::arch::registry::{self, Entry};
+use crate::config;
+use crate::execution::{ExecutionEvent, Log, OperationContext, OperationError, OperationResult, Outcome};
+use crate::filesystem::format_size;
+use crate::history::{self, OperationRecord, RunRecord, SnapshotRecord};
+use crate::privilege::KeepAlive;
+use crate::quarantine;
+use crate::snapshots::{self, Snapshot};
+
+// Runs the operations one after another on a background thread, reporting progress and output through the
+// returned receiver. Credentials have to be sorted out beforehand: root_error is why authentication failed,
+// and keep_alive is held until the last operation is done. Setting cancel skips the operations not started yet.
+pub fn spawn(
+    operations: Vec<ArchOperation>,
+    root_error: Option<OperationError>,
+    keep_alive: Option<KeepAlive>,
+    cancel: Arc<AtomicBool>,
+) -> Receiver<ExecutionEvent> {
+    let (sender, receiver) = mpsc::channel();
+    let log = Log::new(sender);
+    thread::spawn(move || {
+        let _keep_alive = keep_alive;
+        run(&operations, root_error, &cancel, &log);
+        log.send(ExecutionEvent::Done);
+    });
+    receiver
+}
+
+fn run(operations: &[ArchOperation], root_error: Option<OperationError>, cancel: &AtomicBool, log: &Log) {
+    // The lock may have been taken since the confirmation screen checked it, and there is nobody to ask now
+    let lock_error = if operations.iter().any(ArchOperation::uses_pacman_db) {
+        match pacman_lock::check() {
+            LockState::Free => None,
+            state => Some(OperationError::LockHeld(state.describe())),
+        }
+    } else {
+        None
+    };
+
+    let snapshot = if root_error.is_none() && lock_error.is_none() && operations.iter().any(ArchOperation::modifies_packages) {
+        take_pre_snapshot(log)
+    } else {
+        None
+    };
+
+    let mut any_failed = false;
+    let mut records = Vec::new();
+    for (i, operation) in operations.iter().enumerate() {
+        log.send(ExecutionEvent::Started(i));
+        // Checked again as a run from the command line or a timer never went through the menu
+        let unmet = registry::find(operation.id()).and_then(Entry::probe);
+        let result = match (&root_error, &lock_error, &unmet) {
+            _ if cancel.load(Ordering::Relaxed) => OperationResult::skipped(operation.name(), OperationError::Cancelled),
+            (_, _, Some(unmet)) => OperationResult::skipped(operation.name(), unmet.error.clone()),
+            (Some(e), _, _) if operation.needs_root() => OperationResult::skipped(operation.name(), e.clone()),
+            (_, Some(e), _) if operation.uses_pacman_db() => OperationResult::skipped(operation.name(), e.clone()),
+            _ => {
+                log.line(format!("==> {}", operation.name()));
+                let started = Instant::now();
+                let mut ctx = OperationContext::new(operation.name(), Some(log.clone()));
+                let outcome = match operation.execute(&mut ctx) {
+                    Ok(()) => Outcome::Succeeded,
+                    Err(e) => {
+                        any_failed = true;
+                        log.line(format!("Error: {}", e));
+                        Outcome::Failed(e)
+                    }
+                };
+                let result = ctx.finish(outcome, started.elapsed());
+                log.line(format!("==> {}", result.summary()));
+                result
+            }
+        };
+        records.push(OperationRecord::from(&result));
+        log.send(ExecutionEvent::Finished(i, result));
+    }
+
+    let snapshot = snapshot.map(|snapshot| {
+        let post_id = snapshot.finish("debloater: after package operations").unwrap_or_else(|e| {
+            log.line(format!("Could not create the post snapshot: {}", e));
+            None
+        });
+        if any_failed {
+            log.line(format!("Some operations failed. To undo the changes made since the snapshot, run: {}", snapshot.rollback_hint()));
+        }
+        SnapshotRecord { tool: snapshot.tool.name().to_string(), pre_id: snapshot.id, post_id }
+    });
+    if let Some(snapshot) = &snapshot {
+        log.send(ExecutionEvent::Snapshot(snapshot.clone()));
+    }
+
+    if let Err(e) = history::append(&RunRecord::new(records, snapshot)) {
+        log.line(format!("Could not record this run in the history: {}", e));
+    }
+    purge_quarantine(log);
+}
+
+// Old quarantine runs go once a new run is done, never before `debloater undo` or the quarantine screen had a
+// chance to restore them
+fn purge_quarantine(log: &Log) {
+    let retention_days = config::get().quarantine_retention_days;
+    match quarantine::purge_expired(retention_days) {
+        Ok((0, _)) => {}
+        Ok((runs, bytes)) => log.line(format!("Purged {} quarantine runs older than {} days ({})", runs, retention_days, format_size(bytes))),
+        Err(e) => log.line(format!("Error purging the quarantine: {}", e)),
+    }
+}
+
+// Snapshots the system before package removals, on Btrfs systems managed by snapper or timeshift
+fn take_pre_snapshot(log: &Log) -> Option<Snapshot> {
+    let tool = snapshots::detect()?;
+    log.line(format!("Creating a {} snapshot before removing packages...", tool.name()));
+    match snapshots::create_pre_snapshot(tool, "debloater: before package operations") {
+        Ok(snapshot) => {
+            log.line(format!("Created {} snapshot {}", snapshot.tool.name(), snapshot.id));
+            Some(snapshot)
+        }
+        Err(e) => {
+            log.line(format!("Warning: could not create a snapshot, continuing without one: {}", e));
+            None
+        }
+    }
+}
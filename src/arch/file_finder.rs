@@ -0,0 +1,303 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    text::{Line, Span},
+    Frame,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use crate::arch::operations::ArchOperation;
+use crate::disk_usage::{self, FileNode};
+use crate::filesystem::{format_size, home_dir};
+use crate::theme;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileAction {
+    Delete,
+    Quarantine,
+}
+
+enum Stage {
+    EnteringPath,
+    Scanning(Receiver<Result<FileNode, String>>),
+    Browsing,
+}
+
+// What the surrounding TUI should do after a key press
+pub enum FinderEvent {
+    None,
+    Exit,
+    Confirm,
+}
+
+pub struct FileFinder {
+    path_input: String,
+    stage: Stage,
+    root: Option<FileNode>,
+    current: Vec<usize>, // Child indices from the root down to the directory being viewed
+    state: ListState,
+    marks: BTreeMap<PathBuf, (FileAction, u64)>,
+    error: Option<String>,
+}
+
+impl FileFinder {
+    pub fn new() -> FileFinder {
+        FileFinder {
+            path_input: home_dir().map(|home| home.display().to_string()).unwrap_or_else(|| String::from("/")),
+            stage: Stage::EnteringPath,
+            root: None,
+            current: Vec::new(),
+            state: ListState::default(),
+            marks: BTreeMap::new(),
+            error: None,
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        matches!(self.stage, Stage::Scanning(_))
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marks.is_empty()
+    }
+
+    // Picks up the result of a background scan once it is done
+    pub fn poll_scan(&mut self) {
+        let result = match &self.stage {
+            Stage::Scanning(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Err(String::from("Scan stopped unexpectedly")),
+            },
+            _ => return,
+        };
+
+        match result {
+            Ok(root) => {
+                self.root = Some(root);
+                self.current.clear();
+                self.state.select(Some(0));
+                self.stage = Stage::Browsing;
+            }
+            Err(e) => {
+                self.error = Some(e);
+                self.stage = Stage::EnteringPath;
+            }
+        }
+    }
+
+    fn start_scan(&mut self) {
+        let path = PathBuf::from(self.path_input.trim());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(disk_usage::scan(&path));
+        });
+        self.error = None;
+        self.stage = Stage::Scanning(receiver);
+    }
+
+    fn current_dir(&self) -> Option<&FileNode> {
+        let mut node = self.root.as_ref()?;
+        for &index in &self.current {
+            node = node.children.get(index)?;
+        }
+        Some(node)
+    }
+
+    fn selected_node(&self) -> Option<&FileNode> {
+        self.current_dir()?.children.get(self.state.selected()?)
+    }
+
+    fn toggle_mark(&mut self, action: FileAction) {
+        let (path, size) = match self.selected_node() {
+            Some(node) => (node.path.clone(), node.size),
+            None => return,
+        };
+        match self.marks.get(&path) {
+            Some((existing, _)) if *existing == action => {
+                self.marks.remove(&path);
+            }
+            _ => {
+                self.marks.insert(path, (action, size));
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> FinderEvent {
+        match self.stage {
+            Stage::EnteringPath => match key {
+                KeyCode::Esc => return FinderEvent::Exit,
+                KeyCode::Enter if !self.path_input.trim().is_empty() => self.start_scan(),
+                KeyCode::Backspace => {
+                    self.path_input.pop();
+                }
+                KeyCode::Char(c) => self.path_input.push(c),
+                _ => {}
+            },
+            Stage::Scanning(_) => {
+                if key == KeyCode::Esc {
+                    // The scan thread finishes on its own, its result is dropped with the receiver
+                    self.stage = Stage::EnteringPath;
+                }
+            }
+            Stage::Browsing => {
+                let len = self.current_dir().map(|dir| dir.children.len()).unwrap_or(0);
+                match key {
+                    KeyCode::Esc | KeyCode::Char('q') => return FinderEvent::Exit,
+                    KeyCode::Char('c') if self.has_marks() => return FinderEvent::Confirm,
+                    KeyCode::Char('d') => self.toggle_mark(FileAction::Delete),
+                    KeyCode::Char('m') => self.toggle_mark(FileAction::Quarantine),
+                    KeyCode::Char('s') => self.stage = Stage::EnteringPath,
+                    KeyCode::Down if len > 0 => {
+                        let i = self.state.selected().map(|i| (i + 1) % len).unwrap_or(0);
+                        self.state.select(Some(i));
+                    }
+                    KeyCode::Up if len > 0 => {
+                        let i = self.state.selected().map(|i| if i == 0 { len - 1 } else { i - 1 }).unwrap_or(0);
+                        self.state.select(Some(i));
+                    }
+                    KeyCode::Enter | KeyCode::Right => {
+                        if let (Some(i), Some(true)) = (self.state.selected(), self.selected_node().map(|n| n.is_dir)) {
+                            self.current.push(i);
+                            self.state.select(Some(0));
+                        }
+                    }
+                    KeyCode::Backspace | KeyCode::Left => {
+                        if let Some(i) = self.current.pop() {
+                            self.state.select(Some(i));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        FinderEvent::None
+    }
+
+    // Operations for the marked paths. Paths inside a marked directory are dropped, since they go with it.
+    pub fn operations(&self) -> Vec<ArchOperation> {
+        let mut delete = Vec::new();
+        let mut quarantine = Vec::new();
+        for (path, (action, _)) in &self.marks {
+            if self.marks.keys().any(|other| other != path && path.starts_with(other)) {
+                continue;
+            }
+            match action {
+                FileAction::Delete => delete.push(path.clone()),
+                FileAction::Quarantine => quarantine.push(path.clone()),
+            }
+        }
+
+        let mut operations = Vec::new();
+        if !delete.is_empty() {
+            operations.push(ArchOperation::DeletePaths(delete));
+        }
+        if !quarantine.is_empty() {
+            operations.push(ArchOperation::QuarantinePaths(quarantine));
+        }
+        operations
+    }
+
+    // One line per marked path, for the confirmation screen
+    pub fn confirmation_lines(&self) -> Vec<String> {
+        self.marks
+            .iter()
+            .map(|(path, (action, size))| {
+                let verb = match action {
+                    FileAction::Delete => "Delete",
+                    FileAction::Quarantine => "Quarantine",
+                };
+                format!("{} {} ({})", verb, path.display(), format_size(*size))
+            })
+            .collect()
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let title = match &self.stage {
+            Stage::EnteringPath => String::from("Find large files: enter a path to scan"),
+            Stage::Scanning(_) => format!("Scanning {}...", self.path_input.trim()),
+            Stage::Browsing => match self.current_dir() {
+                Some(dir) => format!("{} ({})", dir.path.display(), format_size(dir.size)),
+                None => String::new(),
+            },
+        };
+        let header = Paragraph::new(title)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL).title("Large file finder"));
+        f.render_widget(header, chunks[0]);
+
+        match self.stage {
+            Stage::EnteringPath => {
+                let mut lines = vec![Line::from(vec![
+                    Span::raw("Path: "),
+                    Span::styled(format!("{}_", self.path_input), theme::get().input),
+                ])];
+                if let Some(error) = &self.error {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(Span::styled(error.clone(), theme::get().error)));
+                }
+                let input = Paragraph::new(lines).block(Block::default().borders(Borders::ALL));
+                f.render_widget(input, chunks[1]);
+            }
+            Stage::Scanning(_) => {
+                let waiting = Paragraph::new("Scanning, this can take a while for large directories...")
+                    .block(Block::default().borders(Borders::ALL));
+                f.render_widget(waiting, chunks[1]);
+            }
+            Stage::Browsing => self.draw_tree(f, chunks[1]),
+        }
+
+        let help = match self.stage {
+            Stage::EnteringPath => "Enter: scan  Esc: back",
+            Stage::Scanning(_) => "Esc: cancel",
+            Stage::Browsing => "Enter/→: open  ←: up  d: delete  m: quarantine  s: new scan  c: confirm  Esc: back",
+        };
+        let footer = Paragraph::new(help)
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+    }
+
+    fn draw_tree<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let dir = match self.current_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let items: Vec<ListItem> = dir.children
+            .iter()
+            .map(|node| {
+                let (mark, style) = match self.mark_for(&node.path) {
+                    Some(FileAction::Delete) => ("[D]", theme::get().error),
+                    Some(FileAction::Quarantine) => ("[Q]", theme::get().notice),
+                    None => ("[ ]", Style::default()),
+                };
+                let percent = (node.size * 100).checked_div(dir.size).unwrap_or(0);
+                let name = if node.is_dir { format!("{}/", node.name) } else { node.name.clone() };
+                ListItem::new(format!("{} {:>10} {:>3}%  {}", mark, format_size(node.size), percent, name)).style(style)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("{} marked", self.marks.len())))
+            .highlight_style(theme::get().highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, area, &mut self.state.clone());
+    }
+
+    fn mark_for(&self, path: &Path) -> Option<FileAction> {
+        self.marks.get(path).map(|(action, _)| *action)
+    }
+}
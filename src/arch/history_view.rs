@@ -0,0 +1,164 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Sparkline},
+    text::{Line, Span},
+    Frame,
+};
+use crate::arch::file_finder::FinderEvent;
+use crate::filesystem::format_size;
+use crate::history::{self, RunRecord};
+use crate::timestamp;
+use crate::theme;
+
+pub struct HistoryView {
+    runs: Vec<RunRecord>, // Oldest first, as in the history file
+    state: ListState,     // Indexes the list, which shows the newest run first
+}
+
+impl HistoryView {
+    pub fn new() -> HistoryView {
+        HistoryView { runs: Vec::new(), state: ListState::default() }
+    }
+
+    pub fn reload(&mut self) {
+        self.runs = history::load();
+        self.state.select(if self.runs.is_empty() { None } else { Some(0) });
+    }
+
+    fn selected_run(&self) -> Option<&RunRecord> {
+        self.state.selected().and_then(|i| self.runs.iter().rev().nth(i))
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> FinderEvent {
+        let len = self.runs.len();
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return FinderEvent::Exit,
+            KeyCode::Down if len > 0 => {
+                let i = self.state.selected().map(|i| (i + 1) % len).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            KeyCode::Up if len > 0 => {
+                let i = self.state.selected().map(|i| if i == 0 { len - 1 } else { i - 1 }).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            _ => {}
+        }
+        FinderEvent::None
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(6),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let total: u64 = self.runs.iter().map(|run| run.bytes_freed).sum();
+        let header = match self.runs.first() {
+            Some(first) => format!(
+                "{} reclaimed over {} runs since {}",
+                format_size(total),
+                self.runs.len(),
+                timestamp::format(first.timestamp)
+            ),
+            None => String::from("No runs recorded yet"),
+        };
+        let header = Paragraph::new(header)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL).title("Run History"));
+        f.render_widget(header, chunks[0]);
+
+        // Space reclaimed so far after each run
+        let cumulative: Vec<u64> = self.runs
+            .iter()
+            .scan(0, |total, run| {
+                *total += run.bytes_freed;
+                Some(*total)
+            })
+            .collect();
+        let sparkline = Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title("Space reclaimed over time"))
+            .data(&cumulative)
+            .style(theme::get().ok);
+        f.render_widget(sparkline, chunks[1]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(45),
+                Constraint::Percentage(55),
+            ].as_ref())
+            .split(chunks[2]);
+
+        let items: Vec<ListItem> = self.runs
+            .iter()
+            .rev()
+            .map(|run| {
+                let failed = run.count("failed");
+                let style = if failed > 0 { theme::get().error } else { Style::default() };
+                ListItem::new(format!(
+                    "{}  {:>10}  {} ops",
+                    timestamp::format(run.timestamp),
+                    format_size(run.bytes_freed),
+                    run.operations.len()
+                ))
+                .style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Runs"))
+            .highlight_style(theme::get().highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, body[0], &mut self.state.clone());
+
+        let details: Vec<Line> = match self.selected_run() {
+            Some(run) => {
+                let mut lines = vec![
+                    Line::from(format!("Host: {} ({})", run.hostname, run.distro)),
+                    Line::from(format!(
+                        "{} succeeded, {} failed, {} skipped, freed {}",
+                        run.count("succeeded"),
+                        run.count("failed"),
+                        run.count("skipped"),
+                        format_size(run.bytes_freed)
+                    )),
+                ];
+                if let Some(snapshot) = &run.snapshot {
+                    lines.push(Line::from(format!("Snapshot: {}", snapshot.describe())));
+                }
+                lines.push(Line::from(""));
+                for operation in &run.operations {
+                    let style = match operation.outcome.as_str() {
+                        "succeeded" => theme::get().ok,
+                        "failed" => theme::get().error,
+                        _ => theme::get().notice,
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{:<10}", operation.outcome), style),
+                        Span::raw(format!("{}, {}", operation.name, format_size(operation.bytes_freed))),
+                    ]));
+                    if let Some(error) = &operation.error {
+                        lines.push(Line::from(Span::styled(format!("          {}", error), style)));
+                    }
+                }
+                lines
+            }
+            None => Vec::new(),
+        };
+        let details = Paragraph::new(details)
+            .wrap(tui::widgets::Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Details"));
+        f.render_widget(details, body[1]);
+
+        let footer = Paragraph::new("Up/Down: select a run  Esc: back")
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[3]);
+    }
+}
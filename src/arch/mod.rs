@@ -1,3 +1,16 @@
+pub mod dashboard;
+pub mod duplicate_finder;
+pub mod executor;
+pub mod file_finder;
+pub mod history_view;
 
 pub mod operations;
+pub mod pacman_lock;
+pub mod prerequisites;
+pub mod profile_picker;
+pub mod progress;
+pub mod quarantine_view;
+pub mod registry;
+pub mod schedule_view;
+pub mod settings_editor;
 pub mod tui;
\ No newline at end of file
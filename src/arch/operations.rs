@@ -1,157 +1,384 @@
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use colored::Colorize;
+use std::time::{Duration, SystemTime};
+use crate::arch::registry;
+use crate::browsers;
+use crate::config;
+use crate::duplicates::{self, DuplicateResolution};
+use crate::execution::{OperationContext, OperationError};
+use crate::filesystem::{dir_size, format_size, matches_wildcard, path_error, remove_path, xdg_dir};
+use crate::privilege;
+use crate::quarantine;
+use crate::thumbnails;
+use crate::timestamp::SECONDS_PER_DAY;
+use crate::trash;
 
 pub enum ArchOperation {
-    CleanCache,
+    CleanCache { keep: u32 },
     RemoveOrphaned,
     ManualPackageRemoval,
     RepairFlatpak,
     RemoveUnusedFlatpak,
     ManualFlatpakRemoval,
     ChangeFlatpakDir,
-    ClearSystemdJournal,
+    ClearSystemdJournal { vacuum_time: String },
     CleanGeneralLogs,
-    CleanUserCache,
+    CleanUserCache { min_age_days: u64, exclude: Vec<String> }, // Names in the cache to leave alone, with * wildcards
+    CleanBrowserCaches,
+    CleanStaleThumbnails,
+    EmptyTrash { min_age_days: u64 },
     ManagePacFiles,
     RemoveOrphanedConfigs,
+    DeletePaths(Vec<PathBuf>),
+    QuarantinePaths(Vec<PathBuf>),
+    DeduplicateFiles(Vec<DuplicateResolution>),
 }
 
 impl ArchOperation {
-    pub fn execute(&self) -> Result<(), String> {
+    // Operations that can leave the system unbootable if they remove the wrong thing, and get a snapshot first
+    pub fn modifies_packages(&self) -> bool {
+        matches!(self, Self::RemoveOrphaned | Self::ManualPackageRemoval)
+    }
+
+    // Operations that can remove more than their name says, and are left out of schedules unless asked for
+    pub fn needs_opt_in(&self) -> bool {
+        matches!(self, Self::RemoveOrphaned)
+    }
+
+    // What the operation is about to remove, where that isn't clear from its name, to be shown before it runs
+    pub fn removal_plan(&self) -> Option<Result<Vec<String>, OperationError>> {
+        match self {
+            Self::RemoveOrphaned => Some(orphan_removal_plan()),
+            _ => None,
+        }
+    }
+
+    // Operations that need the pacman database lock, or that would disturb a running pacman
+    pub fn uses_pacman_db(&self) -> bool {
+        matches!(self, Self::CleanCache { .. } | Self::RemoveOrphaned | Self::ManualPackageRemoval)
+    }
+
+    // Operations that run commands as root, and need credentials before the batch starts. Snapshots are taken
+    // as root too, so everything that gets one is included.
+    pub fn needs_root(&self) -> bool {
+        self.modifies_packages()
+            || matches!(
+                self,
+                Self::CleanCache { .. } | Self::RepairFlatpak | Self::RemoveUnusedFlatpak | Self::ClearSystemdJournal { .. } | Self::ManagePacFiles
+            )
+    }
+
+    pub fn name(&self) -> &'static str {
+        registry::find(self.id()).map_or(self.id(), |entry| entry.name)
+    }
+
+    // Stable identifier used on the command line and in JSON output, the id of the registry entry building it
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::CleanCache { .. } => "clean-package-cache",
+            Self::RemoveOrphaned => "remove-orphaned-packages",
+            Self::ManualPackageRemoval => "manual-package-removal",
+            Self::RepairFlatpak => "repair-flatpak",
+            Self::RemoveUnusedFlatpak => "remove-unused-flatpak",
+            Self::ManualFlatpakRemoval => "manual-flatpak-removal",
+            Self::ChangeFlatpakDir => "change-flatpak-dir",
+            Self::ClearSystemdJournal { .. } => "clear-systemd-journal",
+            Self::CleanGeneralLogs => "clean-general-logs",
+            Self::CleanUserCache { .. } => "clean-user-cache",
+            Self::CleanBrowserCaches => "clean-browser-caches",
+            Self::CleanStaleThumbnails => "clean-stale-thumbnails",
+            Self::EmptyTrash { .. } => "empty-trash",
+            Self::ManagePacFiles => "manage-pac-files",
+            Self::RemoveOrphanedConfigs => "remove-orphaned-configs",
+            Self::DeletePaths(_) => "delete-paths",
+            Self::QuarantinePaths(_) => "quarantine-paths",
+            Self::DeduplicateFiles(_) => "deduplicate-files",
+        }
+    }
+
+    // Operations that can run without picking files first, by their id, set up as the config says
+    pub fn from_id(id: &str) -> Option<ArchOperation> {
+        registry::find(id)?.build(config::get())
+    }
+
+    // How much the operation is expected to free, where that can be worked out without running it
+    pub fn estimate_bytes(&self) -> Option<u64> {
+        match self {
+            Self::CleanUserCache { min_age_days, exclude } => Some(
+                user_cache_entries(*min_age_days, exclude)
+                    .iter()
+                    .filter(|path| !config::get().is_excluded(path))
+                    .map(|path| dir_size(path))
+                    .sum(),
+            ),
+            Self::CleanBrowserCaches => Some(browsers::cache_size()),
+            Self::CleanStaleThumbnails => thumbnails::find_stale().ok().map(|stale| stale.iter().map(|(_, size)| size).sum()),
+            Self::EmptyTrash { min_age_days } => Some(trash::summarize(*min_age_days).expired_bytes),
+            Self::DeletePaths(paths) | Self::QuarantinePaths(paths) => Some(paths.iter().map(|path| dir_size(path)).sum()),
+            Self::DeduplicateFiles(resolutions) => Some(resolutions.iter().map(|r| r.size * r.duplicates.len() as u64).sum()),
+            _ => None,
+        }
+    }
+
+    pub fn execute(&self, ctx: &mut OperationContext) -> Result<(), OperationError> {
         match self {
-            Self::CleanCache => clean_package_cache(),
-            Self::RemoveOrphaned => remove_orphaned_packages(),
-            Self::ManualPackageRemoval => manual_package_removal(),
-            Self::RepairFlatpak => repair_flatpak(),
-            Self::RemoveUnusedFlatpak => remove_unused_flatpak(),
-            Self::ManualFlatpakRemoval => manual_flatpak_removal(),
-            Self::ChangeFlatpakDir => change_flatpak_dir(),
-            Self::ClearSystemdJournal => clear_systemd_journal(),
-            Self::CleanGeneralLogs => clean_general_logs(),
-            Self::CleanUserCache => clean_user_cache(),
-            Self::ManagePacFiles => manage_pac_files(),
-            Self::RemoveOrphanedConfigs => remove_orphaned_configs(),
+            Self::CleanCache { keep } => clean_package_cache(ctx, *keep),
+            Self::RemoveOrphaned => remove_orphaned_packages(ctx),
+            Self::ManualPackageRemoval => manual_package_removal(ctx),
+            Self::RepairFlatpak => repair_flatpak(ctx),
+            Self::RemoveUnusedFlatpak => remove_unused_flatpak(ctx),
+            Self::ManualFlatpakRemoval => manual_flatpak_removal(ctx),
+            Self::ChangeFlatpakDir => change_flatpak_dir(ctx),
+            Self::ClearSystemdJournal { vacuum_time } => clear_systemd_journal(ctx, vacuum_time),
+            Self::CleanGeneralLogs => clean_general_logs(ctx),
+            Self::CleanUserCache { min_age_days, exclude } => clean_user_cache(ctx, *min_age_days, exclude),
+            Self::CleanBrowserCaches => clean_browser_caches(ctx),
+            Self::CleanStaleThumbnails => clean_stale_thumbnails(ctx),
+            Self::EmptyTrash { min_age_days } => empty_trash(ctx, *min_age_days),
+            Self::ManagePacFiles => manage_pac_files(ctx),
+            Self::RemoveOrphanedConfigs => remove_orphaned_configs(ctx),
+            Self::DeletePaths(paths) => delete_paths(ctx, paths),
+            Self::QuarantinePaths(paths) => quarantine_paths(ctx, paths),
+            Self::DeduplicateFiles(resolutions) => deduplicate_files(ctx, resolutions),
         }
     }
 }
 
-fn clean_package_cache() -> Result<(), String> {
-    println!("Running Operation: {}", "Clean package cache".bold().green());
-    println!("Checking for pacman-contrib package...");
+pub const PACKAGE_CACHE: &str = "/var/cache/pacman/pkg";
+const JOURNAL_DIR: &str = "/var/log/journal";
+
+// Number of entries and total size of a directory, to tell what a command run on it removed
+fn dir_usage(path: &Path) -> (usize, u64) {
+    let entries = fs::read_dir(path).map(|entries| entries.count()).unwrap_or(0);
+    (entries, dir_size(path))
+}
+
+fn record_usage_change(ctx: &mut OperationContext, before: (usize, u64), after: (usize, u64)) {
+    ctx.removed(before.0.saturating_sub(after.0), before.1.saturating_sub(after.1));
+}
+
+fn clean_package_cache(ctx: &mut OperationContext, keep: u32) -> Result<(), OperationError> {
+    let before = dir_usage(Path::new(PACKAGE_CACHE));
+    ctx.run(privilege::command("paccache").args(["-r", "-k", &keep.to_string()]))?;
+    record_usage_change(ctx, before, dir_usage(Path::new(PACKAGE_CACHE)));
+    Ok(())
+}
+
+// Packages `pacman -Rns` removes for the orphans: the orphans themselves and whatever only they depend on
+pub fn orphan_removal_plan() -> Result<Vec<String>, OperationError> {
+    let orphans = orphaned_packages()?;
+    if orphans.is_empty() {
+        return Ok(orphans);
+    }
+    // Printing what would be removed needs neither root nor the database lock
     let output = Command::new("pacman")
-        .args(["-Qs", "pacman-contrib"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    
-    let output = Command::new("sudo")
-        .args(["paccache", "-r"])
+        .args(["-Rns", "--print", "--print-format", "%n"])
+        .args(&orphans)
         .output()
-        .map_err(|e| e.to_string())?;
-
-    if output.status.success() {
-        println!("Operation {} {}", "Clean package cache".bold().green(), "completed successfully".green());
-        Ok(())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+        .map_err(|e| OperationError::spawn_failed("pacman", e))?;
+    if !output.status.success() {
+        return Err(OperationError::NonZeroExit {
+            command: String::from("pacman -Rns --print"),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
     }
+    Ok(String::from_utf8_lossy(&output.stdout).split_whitespace().map(String::from).collect())
 }
 
-fn remove_orphaned_packages() -> Result<(), String> {
-    println!("Running Operation: {}", "Remove orphan packages".bold().green());
-    let output = Command::new("sudo")
-        .args(["pacman", "-Rns", "$(pacman -Qtdq)"])
+fn orphaned_packages() -> Result<Vec<String>, OperationError> {
+    // pacman -Qtdq exits with 1 when there is nothing to list
+    let output = Command::new("pacman")
+        .args(["-Qtdq"])
         .output()
-        .map_err(|e| e.to_string())?;
-    println!("Operation {} {}", "Remove orphan packages".bold().green(), "completed successfully".green());
+        .map_err(|e| OperationError::spawn_failed("pacman", e))?;
+    Ok(String::from_utf8_lossy(&output.stdout).split_whitespace().map(String::from).collect())
+}
+
+fn remove_orphaned_packages(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    let orphans = orphaned_packages()?;
+    if orphans.is_empty() {
+        ctx.line("No orphaned packages found");
+        return Ok(());
+    }
+
+    // Listed before anything is removed, as pacman's own prompt can't be answered from here
+    let plan = orphan_removal_plan()?;
+    ctx.line(format!("Removing {} packages: {}", plan.len(), plan.join(" ")));
+    ctx.run(privilege::command("pacman").args(["-Rns", "--noconfirm"]).args(&orphans))?;
+    ctx.removed(plan.len(), 0);
     Ok(())
 }
 
-fn manual_package_removal() -> Result<(), String> {
-    println!("Beginning Operation: {}", "Manual package removal".bold().green());
-    println!("Manual package features coming soon...");
-    println!("Operation {} {}", "Manual package removal".bold().green(), "completed successfully".green());
+fn manual_package_removal(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.warn("Manual package features coming soon...");
     Ok(())
 }
 
-fn repair_flatpak() -> Result<(), String> {
-    println!("Running Operation: {}", "Repair flatpak libraries".bold().green());
-    let output = Command::new("sudo")
-        .args(["flatpak", "repair"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    println!("Operation {} {}", "Repair flatpak libraries".bold().green(), "completed successfully".green());
+fn repair_flatpak(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.run(privilege::command("flatpak").args(["repair"]))
+}
+
+fn remove_unused_flatpak(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.run(privilege::command("flatpak").args(["uninstall", "--unused", "--noninteractive"]))
+}
+
+fn manual_flatpak_removal(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.warn("Manual package features coming soon...");
     Ok(())
 }
 
-fn remove_unused_flatpak() -> Result<(), String> {
-    println!("Running Operation: {}", "Remove unused libraries".bold().green());
-    let output = Command::new("sudo")
-        .args(["flatpak", "uninstall", "--unused"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    println!("Operation {} {}", "Remove unused libraries".bold().green(), "completed successfully".green());
+fn change_flatpak_dir(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.warn("Feature coming soon...");
     Ok(())
 }
 
-fn manual_flatpak_removal() -> Result<(), String> {
-    println!("Beginning Operation: {}", "Manual flatpak removal".bold().green());
-    println!("Manual package features coming soon...");
-    println!("Operation {} {}", "Manual flatpak removal".bold().green(), "completed successfully".green());
+fn clear_systemd_journal(ctx: &mut OperationContext, vacuum_time: &str) -> Result<(), OperationError> {
+    let before = dir_usage(Path::new(JOURNAL_DIR));
+    ctx.run(privilege::command("journalctl").arg(format!("--vacuum-time={}", vacuum_time)))?;
+    // Sizes only, the journal keeps one directory per machine id
+    ctx.removed(0, before.1.saturating_sub(dir_size(Path::new(JOURNAL_DIR))));
+    ctx.line(format!("Cleared all journal logs older than {}", vacuum_time));
     Ok(())
 }
 
-fn change_flatpak_dir() -> Result<(), String> {
-    println!("Running Operation: {}", "Change flatpak installation location".bold().green());
-    println!("Feature coming soon...");
-    println!("Operation {} {}", "Change flatpak installation location".bold().green(), "completed successfully".green());
+fn clean_general_logs(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.warn("Feature coming soon...");
     Ok(())
 }
 
-fn clear_systemd_journal() -> Result<(), String> {
-    println!("Running Operation: {}", "Clear systemd journal".bold().green());
-    let output = Command::new("sudo")
-        .args(["journalctl", "--vacuum-time=1d"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    println!("Cleared all journal logs older than 1 day");
-    println!("Operation {} {}", "Clear systemd journal".bold().green(), "completed successfully".green());
+// Entries of the user cache not touched for min_age_days, leaving out the names in exclude
+fn user_cache_entries(min_age_days: u64, exclude: &[String]) -> Vec<PathBuf> {
+    let cache = match xdg_dir("XDG_CACHE_HOME", ".cache") {
+        Some(cache) => cache,
+        None => return Vec::new(),
+    };
+    // None when the cutoff would be before the epoch, which nothing is older than
+    let cutoff = SystemTime::now().checked_sub(Duration::from_secs(min_age_days.saturating_mul(SECONDS_PER_DAY)));
+    fs::read_dir(&cache)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| !exclude.iter().any(|pattern| matches_wildcard(pattern, &entry.file_name().to_string_lossy())))
+                .filter(|entry| min_age_days == 0 || entry.metadata().and_then(|m| m.modified()).is_ok_and(|modified| cutoff.is_some_and(|cutoff| modified < cutoff)))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn clean_user_cache(ctx: &mut OperationContext, min_age_days: u64, exclude: &[String]) -> Result<(), OperationError> {
+    let cache = xdg_dir("XDG_CACHE_HOME", ".cache").ok_or_else(|| OperationError::Failed(String::from("Could not find the user cache, is $HOME set?")))?;
+    fs::read_dir(&cache).map_err(|e| OperationError::from_io(vec![path_error(&cache, e)]))?;
+    let paths = without_excluded(ctx, &user_cache_entries(min_age_days, exclude));
+
+    // Moved to the quarantine rather than deleted, so an application that lost something important can get it back
+    let (count, bytes) = quarantine::quarantine_paths("Clean user cache", &paths).map_err(OperationError::from_io)?;
+    ctx.removed(count, bytes);
+    ctx.line(format!("Moved {} entries ({}) from {} to the quarantine", count, format_size(bytes), cache.display()));
     Ok(())
 }
 
-fn clean_general_logs() -> Result<(), String> {
-    println!("Running Operation: {}", "Clean general logs".bold().green());
-    println!("Feature coming soon...");
-    println!("Operation {} {}", "Clean general logs".bold().green(), "completed successfully".green());
+fn clean_browser_caches(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    let report = browsers::clean_caches(|path| excluded(ctx, path));
+    for profile in &report.cleaned_profiles {
+        ctx.line(format!("Cleaned {}", profile));
+    }
+    for profile in &report.running_profiles {
+        ctx.warn(format!("Skipped {}, it is running. Close it and run this operation again", profile));
+    }
+    ctx.removed(report.cleaned_profiles.len(), report.bytes_freed);
+    ctx.line(format!("Freed {}", format_size(report.bytes_freed)));
+
+    if report.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(OperationError::from_io(report.errors))
+    }
+}
+
+fn clean_stale_thumbnails(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    let (count, bytes) = thumbnails::remove_stale(|path| excluded(ctx, path)).map_err(OperationError::from_io)?;
+    ctx.removed(count, bytes);
+    ctx.line(format!("Removed {} thumbnails of files that no longer exist, freeing {}", count, format_size(bytes)));
     Ok(())
 }
 
-fn clean_user_cache() -> Result<(), String> {
-    println!("Running Operation: {}", "Clean user cache".bold().green());
-    let output = Command::new("rm")
-        .args(["-rf", "~/.cache/*"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    println!("Operation {} {}", "Clean user cache".bold().green(), "completed successfully".green());
+fn empty_trash(ctx: &mut OperationContext, min_age_days: u64) -> Result<(), OperationError> {
+    let (items, bytes) = trash::empty_older_than(min_age_days, |path| excluded(ctx, path)).map_err(OperationError::from_io)?;
+    ctx.removed(items, bytes);
+    ctx.line(format!("Removed {} trashed items older than {} days, freeing {}", items, min_age_days, format_size(bytes)));
     Ok(())
 }
 
-fn manage_pac_files() -> Result<(), String> {
-    println!("Running Operation: {}", "Manage pac* files".bold().green());
-    let output = Command::new("sudo")
-        .args(["pacdiff"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    println!("Operation {} {}", "Manage pac* files".bold().green(), "completed successfully".green());
+// pacdiff merges interactively, which can't happen inside the TUI, so only list what is waiting to be merged
+fn manage_pac_files(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.run(privilege::command("pacdiff").args(["--output"]))?;
+    ctx.line("Run pacdiff as root in a terminal to merge the files listed above");
     Ok(())
 }
 
-fn remove_orphaned_configs() -> Result<(), String> {
-    println!("Beginning Operation: {}", "Remove orphaned configs".bold().green());
-    print!("Feature coming soon...");
-    println!("Operation {} {}", "Remove orphaned configs".bold().green(), "completed successfully".green());
+fn remove_orphaned_configs(ctx: &mut OperationContext) -> Result<(), OperationError> {
+    ctx.warn("Feature coming soon...");
     Ok(())
 }
 
+// Paths the config excludes are left alone, however they were picked, and the log says so
+fn excluded(ctx: &OperationContext, path: &Path) -> bool {
+    let excluded = config::get().is_excluded(path);
+    if excluded {
+        ctx.line(format!("Skipped {}, it is excluded in the config", path.display()));
+    }
+    excluded
+}
+
+fn without_excluded(ctx: &OperationContext, paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths.iter().filter(|path| !excluded(ctx, path)).cloned().collect()
+}
+
+fn delete_paths(ctx: &mut OperationContext, paths: &[PathBuf]) -> Result<(), OperationError> {
+    let mut errors = Vec::new();
+    for path in &without_excluded(ctx, paths) {
+        let size = dir_size(path);
+        match remove_path(path) {
+            Ok(()) => {
+                ctx.line(format!("Deleted {}", path.display()));
+                ctx.removed(1, size);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(OperationError::from_io(errors))
+    }
+}
 
+fn quarantine_paths(ctx: &mut OperationContext, paths: &[PathBuf]) -> Result<(), OperationError> {
+    let (count, bytes) = quarantine::quarantine_paths("Quarantine marked files", &without_excluded(ctx, paths)).map_err(OperationError::from_io)?;
+    ctx.removed(count, bytes);
+    ctx.line(format!("Moved {} paths ({}) to the quarantine", count, format_size(bytes)));
+    Ok(())
+}
 
+fn deduplicate_files(ctx: &mut OperationContext, resolutions: &[DuplicateResolution]) -> Result<(), OperationError> {
+    let mut errors = Vec::new();
+    for resolution in resolutions {
+        if without_excluded(ctx, &resolution.duplicates).len() < resolution.duplicates.len() {
+            continue;
+        }
+        match duplicates::resolve(resolution) {
+            Ok(bytes) => ctx.removed(resolution.duplicates.len(), bytes),
+            Err(e) => errors.extend(e),
+        }
+    }
 
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(OperationError::from_io(errors))
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use colored::Colorize;
+use crate::config;
+use crate::privilege;
+
+pub const DB_LOCK: &str = "/var/lib/pacman/db.lck";
+
+// Programs that take the pacman database lock, directly or through libalpm
+const PACKAGE_MANAGERS: [&str; 10] = [
+    "pacman", "yay", "paru", "pamac", "pamac-daemon", "packagekitd", "pikaur", "trizen", "aura", "octopi",
+];
+
+pub struct LockHolder {
+    pub pid: u32,
+    pub name: String,
+}
+
+pub enum LockState {
+    Free,
+    // Locked, along with the processes that hold it or could be holding it
+    Held(Vec<LockHolder>),
+    // Locked, but no package manager is running: left behind by a crash or a killed pacman
+    Stale,
+}
+
+impl LockState {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Free => String::from("The pacman database is not locked"),
+            Self::Held(holders) => {
+                let holders: Vec<String> = holders.iter().map(|h| format!("{} (pid {})", h.name, h.pid)).collect();
+                format!("The pacman database is locked by {}", holders.join(", "))
+            }
+            Self::Stale => format!("The pacman database is locked, but no package manager is running. {} is probably left over from a crash.", DB_LOCK),
+        }
+    }
+}
+
+pub fn check() -> LockState {
+    if !Path::new(DB_LOCK).exists() {
+        return LockState::Free;
+    }
+    let holders = lock_holders();
+    if holders.is_empty() {
+        LockState::Stale
+    } else {
+        LockState::Held(holders)
+    }
+}
+
+// libalpm keeps the lock file open for as long as it holds it, so the holder is whoever has it open. Reading
+// another user's file descriptors needs root though, so fall back to looking for running package managers.
+fn lock_holders() -> Vec<LockHolder> {
+    let processes: Vec<(u32, String)> = fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+                    let name = fs::read_to_string(entry.path().join("comm")).ok()?.trim().to_string();
+                    Some((pid, name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let has_lock_open = |pid: u32| {
+        fs::read_dir(format!("/proc/{}/fd", pid))
+            .map(|fds| fds.flatten().any(|fd| fs::read_link(fd.path()).map(|target| target == Path::new(DB_LOCK)).unwrap_or(false)))
+            .unwrap_or(false)
+    };
+    let open_by: Vec<LockHolder> = processes
+        .iter()
+        .filter(|(pid, _)| has_lock_open(*pid))
+        .map(|(pid, name)| LockHolder { pid: *pid, name: name.clone() })
+        .collect();
+    if !open_by.is_empty() {
+        return open_by;
+    }
+
+    processes
+        .into_iter()
+        .filter(|(_, name)| PACKAGE_MANAGERS.contains(&name.as_str()))
+        .map(|(pid, name)| LockHolder { pid, name })
+        .collect()
+}
+
+// Removes a stale lock. Refuses while any package manager is running, since the lock may be theirs.
+pub fn remove_stale_lock() -> Result<(), String> {
+    if !matches!(check(), LockState::Stale) {
+        return Err(String::from("The lock is not stale, refusing to remove it"));
+    }
+    privilege::begin_command(config::get().escalation)?;
+    let output = privilege::command("rm")
+        .args(["-f", DB_LOCK])
+        .stdin(Stdio::inherit())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+// Blocks until the lock is released
+pub fn wait_until_free() {
+    while !matches!(check(), LockState::Free) {
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+// Asks on the terminal what to do about a locked database before package operations run. The questions go to
+// stderr, which keeps stdout clean for JSON output. Returns Ok once the database is free, or Err when the user
+// aborts.
+pub fn resolve_interactively() -> Result<(), String> {
+    loop {
+        let state = check();
+        let stale = match state {
+            LockState::Free => return Ok(()),
+            LockState::Held(_) => false,
+            LockState::Stale => true,
+        };
+
+        eprintln!("{} {}", "Warning:".yellow().bold(), state.describe());
+        if stale {
+            eprint!("[w]ait for it to be released, [r]emove the lock, or [a]bort package operations? ");
+        } else {
+            eprint!("[w]ait for it to be released, or [a]bort package operations? ");
+        }
+        let _ = io::stderr().flush();
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).map_err(|e| e.to_string())?;
+        match answer.trim() {
+            "w" | "W" => {
+                eprintln!("Waiting for the pacman database to be unlocked...");
+                wait_until_free();
+            }
+            "r" | "R" if stale => remove_stale_lock()?,
+            "a" | "A" | "" => return Err(String::from("Aborted, the pacman database is locked")),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use crate::config;
+use crate::execution::OperationError;
+use crate::filesystem::find_in_path;
+use crate::privilege;
+
+// Something an operation needs from the system before it can do anything
+pub enum Requirement {
+    Binary { name: &'static str, package: &'static str }, // A program in $PATH, and the package providing it
+    Service(&'static str),                                 // A systemd unit that has to be running
+    Path(&'static str),                                    // A file or directory that has to exist
+}
+
+// The first requirement of an operation that isn't met
+#[derive(Clone)]
+pub struct Unmet {
+    pub error: OperationError,         // Its Display is the reason shown to the user
+    pub package: Option<&'static str>, // Installing this would meet it
+}
+
+impl Requirement {
+    fn check(&self) -> Result<(), Unmet> {
+        match self {
+            Self::Binary { name, package } if find_in_path(name).is_none() => {
+                let program = if name == package { name.to_string() } else { format!("{} ({})", name, package) };
+                Err(Unmet { error: OperationError::CommandMissing(program), package: Some(package) })
+            }
+            Self::Service(unit) if !is_active(unit) => Err(unavailable(format!("{} is not running", unit))),
+            Self::Path(path) if !Path::new(path).exists() => Err(unavailable(format!("{} does not exist", path))),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn check(requirements: &[Requirement]) -> Option<Unmet> {
+    requirements.iter().find_map(|requirement| requirement.check().err())
+}
+
+fn unavailable(reason: String) -> Unmet {
+    Unmet { error: OperationError::Unavailable(reason), package: None }
+}
+
+fn is_active(unit: &str) -> bool {
+    Command::new("systemctl")
+        .args(["is-active", "--quiet", unit])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// Installs a package with pacman, which asks for confirmation itself, so the terminal has to be in its normal mode
+pub fn install(package: &str) -> Result<(), String> {
+    privilege::begin_command(config::get().escalation)?;
+    let status = privilege::command("pacman")
+        .args(["-S", "--needed", package])
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| format!("pacman: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Installing {} failed", package))
+    }
+}
@@ -0,0 +1,137 @@
+use crossterm::event::KeyCode;
+use std::path::PathBuf;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    text::Line,
+    Frame,
+};
+use crate::config::{self, Profile};
+use crate::theme;
+
+// What the surrounding TUI should do after a key press
+pub enum PickerEvent {
+    None,
+    Exit,
+    Apply(Vec<String>), // Check the operations with these ids, and only those
+    Save(String),       // Save the current selection under this name
+}
+
+pub struct ProfilePicker {
+    profiles: Vec<(String, Profile)>,
+    // Profiles saved while the TUI is open, which the config loaded at startup doesn't have
+    saved: Vec<(String, Profile)>,
+    state: ListState,
+    name_input: Option<String>, // Set while typing the name of a new profile
+    status: Option<(String, bool)>, // Message and whether it is an error
+}
+
+impl ProfilePicker {
+    pub fn new() -> ProfilePicker {
+        ProfilePicker { profiles: Vec::new(), saved: Vec::new(), state: ListState::default(), name_input: None, status: None }
+    }
+
+    pub fn reload(&mut self) {
+        self.profiles = config::get().profiles.iter().map(|(name, profile)| (name.clone(), profile.clone())).collect();
+        // A saved profile overrides a system one of the same name, like the user config does
+        self.profiles.retain(|(name, _)| !self.saved.iter().any(|(saved, _)| saved == name));
+        self.profiles.extend(self.saved.iter().cloned());
+        self.profiles.sort_by(|a, b| a.0.cmp(&b.0));
+        self.state.select(if self.profiles.is_empty() { None } else { Some(0) });
+        self.name_input = None;
+        self.status = None;
+    }
+
+    // Called back by the TUI once it tried to save the selection as a profile
+    pub fn saved(&mut self, name: String, operations: Vec<String>, result: Result<PathBuf, String>) {
+        match result {
+            Ok(path) => {
+                self.status = Some((format!("Saved profile {} to {}", name, path.display()), false));
+                let profile = (name, Profile { description: None, operations, system: false });
+                self.saved.push(profile.clone());
+                self.profiles.retain(|(name, _)| *name != profile.0);
+                self.profiles.push(profile);
+                self.profiles.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            Err(e) => self.status = Some((e, true)),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> PickerEvent {
+        if let Some(name) = &mut self.name_input {
+            match key {
+                KeyCode::Esc => self.name_input = None,
+                KeyCode::Enter => {
+                    let name = name.trim().to_string();
+                    self.name_input = None;
+                    return PickerEvent::Save(name);
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) => name.push(c),
+                _ => {}
+            }
+            return PickerEvent::None;
+        }
+
+        let len = self.profiles.len();
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PickerEvent::Exit,
+            KeyCode::Char('s') => {
+                self.name_input = Some(String::new());
+                self.status = None;
+            }
+            KeyCode::Enter => {
+                if let Some((_, profile)) = self.state.selected().and_then(|i| self.profiles.get(i)) {
+                    return PickerEvent::Apply(profile.operations.clone());
+                }
+            }
+            KeyCode::Down if len > 0 => {
+                let i = self.state.selected().map(|i| (i + 1) % len).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            KeyCode::Up if len > 0 => {
+                let i = self.state.selected().map(|i| if i == 0 { len - 1 } else { i - 1 }).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            _ => {}
+        }
+        PickerEvent::None
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let items: Vec<ListItem> = self.profiles
+            .iter()
+            .map(|(name, profile)| {
+                let mut lines = vec![Line::from(format!("{}  ({})", name, profile.operations.join(", ")))];
+                if let Some(description) = &profile.description {
+                    lines.push(Line::from(format!("    {}", description)));
+                }
+                ListItem::new(lines)
+            })
+            .collect();
+        let title = if self.profiles.is_empty() { "Profiles (none defined yet)" } else { "Profiles" };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::get().highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[0], &mut self.state.clone());
+
+        let footer = match (&self.name_input, &self.status) {
+            (Some(name), _) => Paragraph::new(format!("Name of the new profile: {}_", name)).style(theme::get().input),
+            (None, Some((message, true))) => Paragraph::new(message.clone()).style(theme::get().error),
+            (None, Some((message, false))) => Paragraph::new(message.clone()).style(theme::get().ok),
+            (None, None) => Paragraph::new("Enter: check the profile's operations  s: save the current selection as a profile  Esc: back"),
+        };
+        f.render_widget(footer.alignment(tui::layout::Alignment::Center).block(Block::default().borders(Borders::ALL)), chunks[1]);
+    }
+}
@@ -0,0 +1,215 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    text::{Line, Span},
+    Frame,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::Arc;
+use crate::arch::executor;
+use crate::arch::file_finder::FinderEvent;
+use crate::arch::operations::ArchOperation;
+use crate::execution::{ExecutionEvent, OperationError, OperationResult, Outcome};
+use crate::filesystem::format_size;
+use crate::privilege::KeepAlive;
+use crate::theme;
+
+// How far PageUp and PageDown move the log
+const PAGE: usize = 10;
+
+enum OperationStatus {
+    Pending,
+    Running,
+    Finished(OperationResult),
+}
+
+pub struct ProgressView {
+    names: Vec<&'static str>,
+    statuses: Vec<OperationStatus>,
+    log: Vec<String>,
+    scroll: usize, // Lines scrolled up from the end of the log, 0 follows new output
+    receiver: Option<Receiver<ExecutionEvent>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ProgressView {
+    pub fn start(operations: Vec<ArchOperation>, root_error: Option<OperationError>, keep_alive: Option<KeepAlive>) -> ProgressView {
+        let names = operations.iter().map(ArchOperation::name).collect::<Vec<_>>();
+        let statuses = names.iter().map(|_| OperationStatus::Pending).collect();
+        let cancel = Arc::new(AtomicBool::new(false));
+        ProgressView {
+            names,
+            statuses,
+            log: Vec::new(),
+            scroll: 0,
+            receiver: Some(executor::spawn(operations, root_error, keep_alive, cancel.clone())),
+            cancel,
+        }
+    }
+
+    fn cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn results(&self) -> impl Iterator<Item = &OperationResult> {
+        self.statuses.iter().filter_map(|status| match status {
+            OperationStatus::Finished(result) => Some(result),
+            _ => None,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    // Takes in everything the operations reported since the last call
+    pub fn poll(&mut self) {
+        while let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Ok(ExecutionEvent::Started(i)) => self.statuses[i] = OperationStatus::Running,
+                Ok(ExecutionEvent::Output(line)) => {
+                    self.log.push(line);
+                    // Keep what the user scrolled to in view while new lines come in
+                    if self.scroll > 0 {
+                        self.scroll += 1;
+                    }
+                }
+                Ok(ExecutionEvent::Finished(i, result)) => self.statuses[i] = OperationStatus::Finished(result),
+                // Already in the log, as it was taken
+                Ok(ExecutionEvent::Snapshot(_)) => {}
+                Ok(ExecutionEvent::Done) | Err(TryRecvError::Disconnected) => self.receiver = None,
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> FinderEvent {
+        match key {
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') if !self.is_running() => return FinderEvent::Exit,
+            // The operation that is running finishes first, there is no safe point to stop pacman at
+            KeyCode::Esc => self.cancel.store(true, Ordering::Relaxed),
+            KeyCode::Up => self.scroll = (self.scroll + 1).min(self.log.len()),
+            KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll = (self.scroll + PAGE).min(self.log.len()),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE),
+            KeyCode::Home => self.scroll = self.log.len(),
+            KeyCode::End => self.scroll = 0,
+            _ => {}
+        }
+        FinderEvent::None
+    }
+
+    fn count(&self, matches: fn(&Outcome) -> bool) -> usize {
+        self.results().filter(|result| matches(&result.outcome)).count()
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let title = if self.is_running() { "Running Operations" } else { "Operations Finished" };
+        let title = Paragraph::new(title)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(35),
+                Constraint::Percentage(65),
+            ].as_ref())
+            .split(chunks[1]);
+
+        let statuses: Vec<ListItem> = self.names
+            .iter()
+            .zip(&self.statuses)
+            .map(|(name, status)| {
+                let theme = theme::get();
+                let (label, style) = match status {
+                    OperationStatus::Pending => ("[    ]", theme.dimmed),
+                    OperationStatus::Running => ("[ .. ]", theme.warning),
+                    OperationStatus::Finished(result) => match result.outcome {
+                        Outcome::Succeeded => ("[ ok ]", theme.ok),
+                        Outcome::Failed(_) => ("[fail]", theme.error),
+                        Outcome::Skipped(_) => ("[skip]", theme.notice),
+                    },
+                };
+                let mut lines = vec![Line::from(vec![
+                    Span::styled(label, style.add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(" {}", name)),
+                ])];
+                if let OperationStatus::Finished(result) = status {
+                    let detail = match &result.outcome {
+                        Outcome::Failed(e) | Outcome::Skipped(e) => e.to_string().lines().next().unwrap_or_default().to_string(),
+                        Outcome::Succeeded if result.bytes_freed > 0 => {
+                            format!("{:.1}s, freed {}", result.duration.as_secs_f64(), format_size(result.bytes_freed))
+                        }
+                        Outcome::Succeeded => format!("{:.1}s", result.duration.as_secs_f64()),
+                    };
+                    lines.push(Line::from(Span::styled(format!("       {}", detail), style)));
+                    // Errors other than a failed command don't carry stderr, which may still explain what went wrong
+                    if let Outcome::Failed(e) = &result.outcome {
+                        let last = result.stderr.lines().last().unwrap_or_default();
+                        if !last.is_empty() && !matches!(e, OperationError::NonZeroExit { .. }) {
+                            lines.push(Line::from(Span::styled(format!("       {}", last), theme::get().dimmed)));
+                        }
+                    }
+                    for warning in &result.warnings {
+                        lines.push(Line::from(Span::styled(format!("       {}", warning), theme::get().warning)));
+                    }
+                }
+                ListItem::new(lines)
+            })
+            .collect();
+        let statuses = List::new(statuses).block(Block::default().borders(Borders::ALL).title("Operations"));
+        f.render_widget(statuses, body[0]);
+
+        // Only the lines that fit are handed to the widget, ending where the user scrolled to
+        let height = body[1].height.saturating_sub(2) as usize;
+        let end = self.log.len() - self.scroll.min(self.log.len());
+        let start = end.saturating_sub(height);
+        let log: Vec<Line> = self.log[start..end].iter().map(|line| Line::from(line.as_str())).collect();
+        let log_title = if self.scroll > 0 { format!("Output ({} lines below)", self.scroll) } else { String::from("Output") };
+        let log = Paragraph::new(log).block(Block::default().borders(Borders::ALL).title(log_title));
+        f.render_widget(log, body[1]);
+
+        let footer = if self.is_running() && self.cancelling() {
+            Line::from("Cancelling, waiting for the current operation to finish...")
+        } else if self.is_running() {
+            Line::from("Up/Down, PageUp/PageDown: scroll the output  End: follow new output  Esc: cancel")
+        } else {
+            let failed = self.count(|outcome| matches!(outcome, Outcome::Failed(_)));
+            let style = if failed > 0 { theme::get().error } else { theme::get().ok };
+            Line::from(vec![
+                Span::styled(
+                    format!(
+                        "{} succeeded, {} failed, {} skipped. Freed {}.",
+                        self.count(|outcome| matches!(outcome, Outcome::Succeeded)),
+                        failed,
+                        self.count(|outcome| matches!(outcome, Outcome::Skipped(_))),
+                        format_size(self.results().map(|result| result.bytes_freed).sum()),
+                    ),
+                    style.add_modifier(Modifier::BOLD),
+                ),
+                Span::raw("  Press "),
+                Span::styled("Enter", theme::get().ok.add_modifier(Modifier::BOLD)),
+                Span::raw(" to go back to the menu"),
+            ])
+        };
+        let footer = Paragraph::new(footer)
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+    }
+}
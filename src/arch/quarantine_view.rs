@@ -0,0 +1,144 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use crate::arch::file_finder::FinderEvent;
+use crate::config;
+use crate::filesystem::format_size;
+use crate::quarantine::{self, QuarantineRun};
+use crate::timestamp;
+use crate::theme;
+
+// A row of the flattened list: either a run header or one of its pending entries
+enum Row {
+    Run(usize),
+    Entry(usize, usize),
+}
+
+pub struct QuarantineView {
+    runs: Vec<QuarantineRun>,
+    state: ListState,
+    status: Option<(String, bool)>, // Message and whether it is an error
+}
+
+impl QuarantineView {
+    pub fn new() -> QuarantineView {
+        QuarantineView { runs: Vec::new(), state: ListState::default(), status: None }
+    }
+
+    // Re-reads the quarantine from disk, newest run first
+    pub fn reload(&mut self) {
+        self.runs = quarantine::list_runs();
+        self.runs.reverse();
+        let len = self.rows().len();
+        self.state.select(if len == 0 { None } else { Some(self.state.selected().unwrap_or(0).min(len - 1)) });
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (run_index, run) in self.runs.iter().enumerate() {
+            rows.push(Row::Run(run_index));
+            for (entry_index, entry) in run.manifest.entries.iter().enumerate() {
+                if !entry.restored {
+                    rows.push(Row::Entry(run_index, entry_index));
+                }
+            }
+        }
+        rows
+    }
+
+    fn restore_selected(&mut self) {
+        let row = match self.state.selected().and_then(|i| self.rows().into_iter().nth(i)) {
+            Some(row) => row,
+            None => return,
+        };
+        let result = match row {
+            Row::Run(run_index) => quarantine::restore(&mut self.runs[run_index], None),
+            Row::Entry(run_index, entry_index) => {
+                let path = self.runs[run_index].manifest.entries[entry_index].original_path.clone();
+                quarantine::restore(&mut self.runs[run_index], Some(&[path]))
+            }
+        };
+        self.status = Some(match result {
+            Ok(count) => (format!("Restored {} items", count), false),
+            Err(e) => (e.lines().next().unwrap_or_default().to_string(), true),
+        });
+        self.reload();
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> FinderEvent {
+        let len = self.rows().len();
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return FinderEvent::Exit,
+            KeyCode::Enter | KeyCode::Char('r') => self.restore_selected(),
+            KeyCode::Down if len > 0 => {
+                let i = self.state.selected().map(|i| (i + 1) % len).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            KeyCode::Up if len > 0 => {
+                let i = self.state.selected().map(|i| if i == 0 { len - 1 } else { i - 1 }).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            _ => {}
+        }
+        FinderEvent::None
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let total: u64 = self.runs.iter().map(|run| run.size()).sum();
+        let header = Paragraph::new(format!(
+            "{} runs, {} in total. Runs are deleted for good after {} days.",
+            self.runs.len(),
+            format_size(total),
+            config::get().quarantine_retention_days
+        ))
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .block(Block::default().borders(Borders::ALL).title("Quarantine"));
+        f.render_widget(header, chunks[0]);
+
+        let items: Vec<ListItem> = self.rows()
+            .into_iter()
+            .map(|row| match row {
+                Row::Run(r) => {
+                    let run = &self.runs[r];
+                    ListItem::new(format!(
+                        "{}  {}, {} ({})",
+                        run.name,
+                        run.manifest.operation,
+                        timestamp::format_age(run.manifest.created),
+                        format_size(run.size())
+                    ))
+                    .style(theme::get().heading)
+                }
+                Row::Entry(r, e) => {
+                    let entry = &self.runs[r].manifest.entries[e];
+                    ListItem::new(format!("  {:>10}  {}", format_size(entry.size), entry.original_path.display()))
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL))
+            .highlight_style(theme::get().highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut self.state.clone());
+
+        let footer = match &self.status {
+            Some((message, true)) => Paragraph::new(message.clone()).style(theme::get().error),
+            Some((message, false)) => Paragraph::new(message.clone()).style(theme::get().ok),
+            None => Paragraph::new("Enter: restore the selected run or file  Esc: back"),
+        };
+        f.render_widget(footer.alignment(tui::layout::Alignment::Center).block(Block::default().borders(Borders::ALL)), chunks[2]);
+    }
+}
@@ -0,0 +1,484 @@
+use crate::arch::operations::{ArchOperation, PACKAGE_CACHE};
+use crate::arch::prerequisites::{self, Requirement, Unmet};
+use toml::Value;
+use crate::config::{self, Config};
+
+// Every operation and tool the menu offers, in menu order. The menu, the descriptions, `debloater run` and the
+// config validation are all generated from this list.
+pub static REGISTRY: [Entry; 25] = [
+    Entry {
+        id: "clean-package-cache",
+        name: "Clean package cache",
+        category: Category::PackageManagement,
+        description: "By default, pacman keeps all packages ever installed on the system in a cache. This is useful for downgrading problematic packages, but can take up a lot of space. This operation removes all but the most recent versions of each package from the cache, as many as its settings say.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "paccache", package: "pacman-contrib" }, Requirement::Path(PACKAGE_CACHE)],
+        settings: &[Setting {
+            key: "keep",
+            label: "Package versions to keep",
+            kind: SettingKind::Number,
+            get: |config| config.package_cache_keep.to_string(),
+            set: |config, value| {
+                config.package_cache_keep = config::number(value, 0, 1000)? as u32;
+                Ok(())
+            },
+        }],
+        action: Action::Run(|config| ArchOperation::CleanCache { keep: config.package_cache_keep }),
+    },
+    Entry {
+        id: "remove-orphaned-packages",
+        name: "Remove orphan packages",
+        category: Category::PackageManagement,
+        description: "Your system may have packages that were installed as dependencies for other packages, but are no longer needed. This operation removes these orphaned packages from the system.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "pacman", package: "pacman" }],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::RemoveOrphaned),
+    },
+    Entry {
+        id: "manual-package-removal",
+        name: "Manual package removal",
+        category: Category::PackageManagement,
+        description: "Pick installed packages you no longer use and remove them along with their dependencies.",
+        availability: Availability::ComingSoon,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::ManualPackageRemoval),
+    },
+    Entry {
+        id: "repair-flatpak",
+        name: "Repair flatpak libraries",
+        category: Category::Flatpak,
+        description: "Due to its sandboxed nature, Flatpak applications can sometimes have issues with shared libraries. This operation repairs the libraries used by Flatpak applications, trimming down on disk usage.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "flatpak", package: "flatpak" }],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::RepairFlatpak),
+    },
+    Entry {
+        id: "remove-unused-flatpak",
+        name: "Remove unused libraries",
+        category: Category::Flatpak,
+        description: "Removes Flatpak runtimes and extensions that are no longer used by any installed applications.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "flatpak", package: "flatpak" }],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::RemoveUnusedFlatpak),
+    },
+    Entry {
+        id: "manual-flatpak-removal",
+        name: "Manual flatpak removal",
+        category: Category::Flatpak,
+        description: "Pick installed Flatpak applications you no longer use and remove them along with their data.",
+        availability: Availability::ComingSoon,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::ManualFlatpakRemoval),
+    },
+    Entry {
+        id: "change-flatpak-dir",
+        name: "Change flatpak installation location",
+        category: Category::Flatpak,
+        description: "Move Flatpak installations to another drive with more free space.",
+        availability: Availability::ComingSoon,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::ChangeFlatpakDir),
+    },
+    Entry {
+        id: "clear-systemd-journal",
+        name: "Clear systemd journal",
+        category: Category::CacheAndLogs,
+        description: "Systemd, the system responsible for low-level system maintainence, keeps logs of system events in a journal. While useful for troubleshooting, these logs can take up a lot of space. This operation removes any log older than a day, unless configured otherwise.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "journalctl", package: "systemd" }, Requirement::Service("systemd-journald.service")],
+        settings: &[Setting {
+            key: "vacuum_time",
+            label: "Remove entries older than",
+            kind: SettingKind::Text,
+            get: |config| config.journal_vacuum_time.clone(),
+            set: |config, value| {
+                config.journal_vacuum_time = config::journal_time(value)?;
+                Ok(())
+            },
+        }],
+        action: Action::Run(|config| ArchOperation::ClearSystemdJournal { vacuum_time: config.journal_vacuum_time.clone() }),
+    },
+    Entry {
+        id: "clean-general-logs",
+        name: "Clean general logs",
+        category: Category::CacheAndLogs,
+        description: "Most logs now go to the systemd journal, so there is little left in /var/log to clean. Use Clear systemd journal instead.",
+        availability: Availability::Deprecated,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::CleanGeneralLogs),
+    },
+    Entry {
+        id: "clean-user-cache",
+        name: "Clean user cache",
+        category: Category::CacheAndLogs,
+        description: "Applications store cache data in your home folder, which quickly builds up, but are not required. This operation clears out ~/.cache, saving space. The cache is moved to the quarantine first, and can be restored from there until the quarantine's retention period is over.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[
+            Setting {
+                key: "min_age_days",
+                label: "Minimum days unused",
+                kind: SettingKind::Number,
+                get: |config| config.user_cache_min_age_days.to_string(),
+                set: |config, value| {
+                    config.user_cache_min_age_days = config::number(value, 0, config::MAX_DAYS)?;
+                    Ok(())
+                },
+            },
+            Setting {
+                key: "exclude",
+                label: "Names to leave alone",
+                kind: SettingKind::List,
+                get: |config| config.user_cache_exclude.join(", "),
+                set: |config, value| {
+                    config.user_cache_exclude = config::strings(value)?;
+                    Ok(())
+                },
+            },
+        ],
+        action: Action::Run(|config| ArchOperation::CleanUserCache {
+            min_age_days: config.user_cache_min_age_days,
+            exclude: config.user_cache_exclude.clone(),
+        }),
+    },
+    Entry {
+        id: "clean-browser-caches",
+        name: "Clean browser caches",
+        category: Category::CacheAndLogs,
+        description: "Web browsers keep large disk caches of visited pages, compiled scripts and GPU shaders. This operation clears the caches of Firefox, Chromium, Google Chrome, Brave and Vivaldi profiles, including flatpak installs. Profiles of browsers that are currently running are skipped.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::CleanBrowserCaches),
+    },
+    Entry {
+        id: "clean-stale-thumbnails",
+        name: "Clean stale thumbnails",
+        category: Category::CacheAndLogs,
+        description: "File managers and image viewers cache thumbnails in ~/.cache/thumbnails, and never remove them when the original file is deleted or moved. This operation removes only the thumbnails whose source file no longer exists, keeping the ones that are still useful.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::CleanStaleThumbnails),
+    },
+    Entry {
+        id: "empty-trash",
+        name: "Empty trash",
+        category: Category::CacheAndLogs,
+        description: "Files deleted from a file manager are moved to the trash rather than removed, both in your home folder and on every other mounted drive. This operation permanently deletes anything that has been in the trash for more than 30 days, unless configured otherwise.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[Setting {
+            key: "min_age_days",
+            label: "Minimum days in the trash",
+            kind: SettingKind::Number,
+            get: |config| config.trash_min_age_days.to_string(),
+            set: |config, value| {
+                config.trash_min_age_days = config::number(value, 0, config::MAX_DAYS)?;
+                Ok(())
+            },
+        }],
+        action: Action::Run(|config| ArchOperation::EmptyTrash { min_age_days: config.trash_min_age_days }),
+    },
+    Entry {
+        id: "manage-pac-files",
+        name: "Manage pac* files",
+        category: Category::Config,
+        description: "Lists the .pacnew and .pacsave configuration files that were created during package updates and still need to be merged with pacdiff.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "pacdiff", package: "pacman-contrib" }],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::ManagePacFiles),
+    },
+    Entry {
+        id: "remove-orphaned-configs",
+        name: "Remove orphaned configs",
+        category: Category::Config,
+        description: "Find configuration left behind in your home folder by applications that are no longer installed.",
+        availability: Availability::ComingSoon,
+        requires: &[],
+        settings: &[],
+        action: Action::Run(|_| ArchOperation::RemoveOrphanedConfigs),
+    },
+    Entry {
+        id: "find-large-files",
+        name: "Find large files",
+        category: Category::Files,
+        description: "Scans a directory of your choice and shows everything in it sorted by size, so you can find what is filling up your disk. Press Enter to open the scanner, then mark files or directories to delete or move to the quarantine.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Open(Tool::LargeFiles),
+    },
+    Entry {
+        id: "find-duplicate-files",
+        name: "Find duplicate files",
+        category: Category::Files,
+        description: "Searches the directories of your choice for files with identical contents. Press Enter to open the search, then pick which copy to keep in each group and whether the other copies should be deleted or replaced with hardlinks to it.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Open(Tool::DuplicateFiles),
+    },
+    Entry {
+        id: "quarantine",
+        name: "Quarantine",
+        category: Category::Files,
+        description: "Files removed by operations like Clean user cache are moved to a quarantine rather than deleted, and are only deleted for good once the retention period set in the config is over. Press Enter to see what is in the quarantine and restore whole runs or individual files.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Open(Tool::Quarantine),
+    },
+    Entry {
+        id: "disk-usage",
+        name: "Disk usage",
+        category: Category::Reports,
+        description: "How full each filesystem is, how much space the package cache, journal, Flatpak, user cache, trash and containers take, and which operations are worth running. Press Enter to open it again after a run.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Open(Tool::DiskUsage),
+    },
+    Entry {
+        id: "run-history",
+        name: "Run history",
+        category: Category::Reports,
+        description: "Every run is recorded in ~/.local/state/debloater/history.jsonl, with what each operation did and how much space it freed. Press Enter to browse past runs and see the space reclaimed over time.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Open(Tool::History),
+    },
+    Entry {
+        id: "profiles",
+        name: "Profiles",
+        category: Category::Automation,
+        description: "Named sets of operations from the config, like a weekly cleanup. Press Enter to check the operations of a profile, or to save the current selection as a new one.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Open(Tool::Profiles),
+    },
+    Entry {
+        id: "scheduled-cleanup",
+        name: "Scheduled cleanup",
+        category: Category::Automation,
+        description: "Runs the operations of a category periodically from a systemd timer, without asking anything. Operations that need root run from a system timer and the others from a user timer. Press Enter to choose how often each category is cleaned; `debloater status` shows when the timers last ran.",
+        availability: Availability::Available,
+        requires: &[Requirement::Binary { name: "systemctl", package: "systemd" }],
+        settings: &[],
+        action: Action::Open(Tool::Schedule),
+    },
+    Entry {
+        id: "delete-paths",
+        name: "Delete marked files",
+        category: Category::Hidden,
+        description: "Deletes the files and directories marked in Find large files.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Marked,
+    },
+    Entry {
+        id: "quarantine-paths",
+        name: "Quarantine marked files",
+        category: Category::Hidden,
+        description: "Moves the files and directories marked in Find large files to the quarantine.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Marked,
+    },
+    Entry {
+        id: "deduplicate-files",
+        name: "Deduplicate files",
+        category: Category::Hidden,
+        description: "Deletes or hardlinks the extra copies picked in Find duplicate files.",
+        availability: Availability::Available,
+        requires: &[],
+        settings: &[],
+        action: Action::Marked,
+    },
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Category {
+    PackageManagement,
+    Flatpak,
+    CacheAndLogs,
+    Config,
+    Files,
+    Reports,
+    Automation,
+    Hidden, // Not in the menu, eg. operations built from the files marked in a finder
+}
+
+impl Category {
+    // In menu order
+    pub const MENU: [Category; 7] = [
+        Self::PackageManagement,
+        Self::Flatpak,
+        Self::CacheAndLogs,
+        Self::Config,
+        Self::Files,
+        Self::Reports,
+        Self::Automation,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PackageManagement => "Package management",
+            Self::Flatpak => "Flatpak management",
+            Self::CacheAndLogs => "Cache and logs",
+            Self::Config => "Config",
+            Self::Files => "Files",
+            Self::Reports => "Reports",
+            Self::Automation => "Automation",
+            Self::Hidden => "",
+        }
+    }
+
+    // Stable name for things stored outside the program, eg. schedule units
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::PackageManagement => "package-management",
+            Self::Flatpak => "flatpak",
+            Self::CacheAndLogs => "cache-and-logs",
+            Self::Config => "config",
+            Self::Files => "files",
+            Self::Reports => "reports",
+            Self::Automation => "automation",
+            Self::Hidden => "hidden",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Availability {
+    Available,
+    ComingSoon, // Listed so the plans are visible, but can't be selected
+    Deprecated, // Being phased out, can't be selected any more
+}
+
+impl Availability {
+    // Shown after the name in the menu
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Self::Available => "",
+            Self::ComingSoon => " (coming soon)",
+            Self::Deprecated => " (deprecated)",
+        }
+    }
+}
+
+// Interactive tools that open a screen of their own instead of being selected
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    LargeFiles,
+    DuplicateFiles,
+    Quarantine,
+    DiskUsage,
+    History,
+    Profiles,
+    Schedule,
+}
+
+// A setting of an operation, read from [operations.<id>] in the config and editable in the TUI
+pub struct Setting {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub kind: SettingKind,
+    get: fn(&Config) -> String,                         // The value as it would be typed in the TUI
+    set: fn(&mut Config, &Value) -> Result<(), String>, // Checks a value from the config file and stores it
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SettingKind {
+    Number,
+    Text,
+    List, // Of strings, typed separated by commas in the TUI
+}
+
+impl Setting {
+    pub fn get(&self, config: &Config) -> String {
+        (self.get)(config)
+    }
+
+    pub fn set(&self, config: &mut Config, value: &Value) -> Result<(), String> {
+        (self.set)(config, value)
+    }
+
+    // Changes the setting to a value typed in the TUI, checked the same way as in the config file
+    pub fn set_text(&self, config: &mut Config, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        let value = match self.kind {
+            SettingKind::Number => text.parse::<i64>().map(Value::Integer).unwrap_or_else(|_| Value::String(text.to_string())),
+            SettingKind::Text => Value::String(text.to_string()),
+            SettingKind::List => Value::Array(
+                text.split(',').map(str::trim).filter(|item| !item.is_empty()).map(|item| Value::String(item.to_string())).collect(),
+            ),
+        };
+        self.set(config, &value)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Run(fn(&Config) -> ArchOperation), // Builds the operation, with its settings taken from the config
+    Open(Tool),
+    Marked, // Built by a finder from what was marked in it
+}
+
+pub struct Entry {
+    pub id: &'static str, // Used on the command line, in the config and in JSON output, never change one once released
+    pub name: &'static str,
+    pub category: Category,
+    pub description: &'static str,
+    pub availability: Availability,
+    pub requires: &'static [Requirement], // Checked when the TUI starts and before running
+    pub settings: &'static [Setting],
+    pub action: Action,
+}
+
+impl Entry {
+    // Whether it can be checked in the menu, run from the command line and put in a profile or schedule
+    pub fn is_runnable(&self) -> bool {
+        self.availability == Availability::Available && matches!(self.action, Action::Run(_))
+    }
+
+    // The first prerequisite that isn't met, if any
+    pub fn probe(&self) -> Option<Unmet> {
+        prerequisites::check(self.requires)
+    }
+
+    pub fn build(&self, config: &Config) -> Option<ArchOperation> {
+        match self.action {
+            Action::Run(build) if self.is_runnable() => Some(build(config)),
+            _ => None,
+        }
+    }
+}
+
+pub fn find(id: &str) -> Option<&'static Entry> {
+    REGISTRY.iter().find(|entry| entry.id == id)
+}
+
+pub fn in_category(category: Category) -> impl Iterator<Item = &'static Entry> {
+    REGISTRY.iter().filter(move |entry| entry.category == category)
+}
+
+pub fn runnable() -> impl Iterator<Item = &'static Entry> {
+    REGISTRY.iter().filter(|entry| entry.is_runnable())
+}
+
+pub fn is_runnable(id: &str) -> bool {
+    find(id).is_some_and(Entry::is_runnable)
+}
@@ -0,0 +1,204 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    text::{Line, Span},
+    Frame,
+};
+use crate::arch::file_finder::FinderEvent;
+use crate::arch::operations::ArchOperation;
+use crate::arch::registry::{self, Category};
+use crate::schedule::{self, Installed, Kind, Schedule, Scope, FREQUENCIES};
+use crate::theme;
+
+struct CategorySchedule {
+    category: &'static str,
+    name: String,              // Of the units, eg. debloater-category-cache-and-logs.timer
+    ids: Vec<String>,          // The operations in the category that can run unattended
+    left_out: Vec<String>,     // Those that only run from a timer when asked for on the command line
+    frequency: Option<String>, // As installed
+    pending: Option<String>,   // As chosen on this screen
+}
+
+impl CategorySchedule {
+    fn changed(&self) -> bool {
+        self.frequency != self.pending
+    }
+}
+
+pub struct ScheduleView {
+    categories: Vec<CategorySchedule>,
+    installed: Vec<Installed>,
+    state: ListState,
+    errors: Vec<String>,
+}
+
+impl ScheduleView {
+    pub fn new() -> ScheduleView {
+        ScheduleView { categories: Vec::new(), installed: Vec::new(), state: ListState::default(), errors: Vec::new() }
+    }
+
+    pub fn reload(&mut self) {
+        self.installed = schedule::installed();
+        self.categories = Category::MENU
+            .iter()
+            .map(|category| {
+                let (left_out, ids): (Vec<String>, Vec<String>) = registry::in_category(*category)
+                    .filter(|entry| entry.is_runnable())
+                    .map(|entry| entry.id.to_string())
+                    .partition(|id| ArchOperation::from_id(id).is_some_and(|operation| operation.needs_opt_in()));
+                (category, ids, left_out)
+            })
+            .filter(|(_, ids, _)| !ids.is_empty())
+            .map(|(category, ids, left_out)| {
+                let name = category.id().to_string();
+                let frequency = self
+                    .installed
+                    .iter()
+                    .find(|installed| installed.schedule.kind == Kind::Category && installed.schedule.name == name)
+                    .map(|installed| installed.schedule.frequency.clone());
+                CategorySchedule { category: category.label(), name, ids, left_out, pending: frequency.clone(), frequency }
+            })
+            .collect();
+        self.state.select(if self.categories.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.categories.iter().any(CategorySchedule::changed)
+    }
+
+    // Moves the selected category to the next (or previous) of off, daily, weekly and monthly
+    fn cycle(&mut self, forward: bool) {
+        let Some(category) = self.state.selected().and_then(|i| self.categories.get_mut(i)) else { return };
+        let current = category.pending.as_deref().and_then(|pending| FREQUENCIES.iter().position(|f| *f == pending));
+        let next = match (current, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(FREQUENCIES.len() - 1),
+            (Some(i), true) if i + 1 < FREQUENCIES.len() => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+        category.pending = next.map(|i| FREQUENCIES[i].to_string());
+    }
+
+    // Replaces the schedule of every changed category. Operations that need root go in a system timer and the
+    // others in a user timer, so that user caches are cleaned for the user and not for root.
+    // System timers ask for a password, so the terminal has to be in its normal mode.
+    pub fn apply(&mut self) {
+        self.errors.clear();
+        for category in self.categories.iter().filter(|category| category.changed()) {
+            for scope in [Scope::System, Scope::User] {
+                if let Err(e) = schedule::uninstall(Kind::Category, &category.name, scope) {
+                    self.errors.push(format!("{}: {}", category.category, e));
+                }
+            }
+            let Some(frequency) = &category.pending else { continue };
+            let (root, user): (Vec<&String>, Vec<&String>) = category
+                .ids
+                .iter()
+                .partition(|id| ArchOperation::from_id(id).is_some_and(|operation| operation.needs_root()));
+            for (scope, ids) in [(Scope::System, root), (Scope::User, user)] {
+                if ids.is_empty() {
+                    continue;
+                }
+                let schedule = Schedule {
+                    kind: Kind::Category,
+                    name: category.name.clone(),
+                    scope,
+                    frequency: frequency.clone(),
+                    args: [String::from("run")].into_iter().chain(ids.into_iter().cloned()).collect(),
+                };
+                if let Err(e) = schedule::install(&schedule) {
+                    self.errors.push(format!("{}: {}", category.category, e));
+                }
+            }
+        }
+        let selected = self.state.selected();
+        self.reload();
+        self.state.select(selected);
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> FinderEvent {
+        let len = self.categories.len();
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return FinderEvent::Exit,
+            KeyCode::Char('s') if self.has_changes() => return FinderEvent::Confirm,
+            KeyCode::Enter | KeyCode::Right => self.cycle(true),
+            KeyCode::Left => self.cycle(false),
+            KeyCode::Down if len > 0 => {
+                let i = self.state.selected().map(|i| (i + 1) % len).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            KeyCode::Up if len > 0 => {
+                let i = self.state.selected().map(|i| if i == 0 { len - 1 } else { i - 1 }).unwrap_or(0);
+                self.state.select(Some(i));
+            }
+            _ => {}
+        }
+        FinderEvent::None
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(8),
+                Constraint::Length(3),
+            ].as_ref())
+            .split(area);
+
+        let items: Vec<ListItem> = self.categories
+            .iter()
+            .map(|category| {
+                let frequency = category.pending.as_deref().unwrap_or("off");
+                let style = if category.changed() { theme::get().input } else { Style::default() };
+                let marker = if category.changed() { " (changed)" } else { "" };
+                ListItem::new(format!("{:<24} {}{}", category.category, frequency, marker)).style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Scheduled cleanup"))
+            .highlight_style(theme::get().highlight)
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[0], &mut self.state.clone());
+
+        let mut details: Vec<Line> = self.errors
+            .iter()
+            .map(|error| Line::from(Span::styled(error.clone(), theme::get().error)))
+            .collect();
+        if let Some(category) = self.state.selected().and_then(|i| self.categories.get(i)) {
+            details.push(Line::from(format!("Runs: {}", category.ids.join(", "))));
+            if !category.left_out.is_empty() {
+                details.push(Line::from(format!(
+                    "Leaves out {}, schedule it in a profile with `debloater install-schedule PROFILE --allow-package-removal`",
+                    category.left_out.join(", ")
+                )));
+            }
+            for installed in self.installed.iter().filter(|installed| installed.schedule.kind == Kind::Category && installed.schedule.name == category.name) {
+                let last = match (&installed.last_run, installed.last_result.as_deref()) {
+                    (Some(last), Some("success")) => format!("last run {}, succeeded", last),
+                    (Some(last), Some(result)) => format!("last run {}, failed ({})", last, result),
+                    (Some(last), None) => format!("last run {}", last),
+                    (None, _) => String::from("never run"),
+                };
+                details.push(Line::from(format!(
+                    "{} timer: next run {}, {}",
+                    installed.schedule.scope.name(),
+                    installed.next_run.as_deref().unwrap_or("not scheduled"),
+                    last
+                )));
+            }
+        }
+        let details = Paragraph::new(details)
+            .wrap(tui::widgets::Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Details"));
+        f.render_widget(details, chunks[1]);
+
+        let footer = Paragraph::new("Left/Right: change the frequency  s: save the changes  Esc: back")
+            .alignment(tui::layout::Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[2]);
+    }
+}
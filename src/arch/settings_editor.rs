@@ -0,0 +1,105 @@
+use crossterm::event::KeyCode;
+use tui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    text::{Line, Span},
+    Frame,
+};
+use crate::arch::file_finder::FinderEvent;
+use crate::arch::registry::{Entry, Setting, SettingKind};
+use crate::config::Config;
+use crate::theme;
+
+struct Field {
+    setting: &'static Setting,
+    text: String,
+    error: Option<String>,
+}
+
+// Dialog over the operation list for changing the settings of one operation for this session. The values are
+// checked like the config file's when saved, and nothing changes until all of them are valid.
+pub struct SettingsEditor {
+    entry: Option<&'static Entry>,
+    fields: Vec<Field>,
+    selected: usize,
+}
+
+impl SettingsEditor {
+    pub fn new() -> SettingsEditor {
+        SettingsEditor { entry: None, fields: Vec::new(), selected: 0 }
+    }
+
+    pub fn open(&mut self, entry: &'static Entry, settings: &Config) {
+        self.entry = Some(entry);
+        self.fields = entry
+            .settings
+            .iter()
+            .map(|setting| Field { setting, text: setting.get(settings), error: None })
+            .collect();
+        self.selected = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode, settings: &mut Config) -> FinderEvent {
+        if self.entry.is_none() {
+            return FinderEvent::Exit;
+        }
+        let len = self.fields.len();
+        match key {
+            KeyCode::Esc => return FinderEvent::Exit,
+            KeyCode::Enter => {
+                let mut updated = settings.clone();
+                for field in &mut self.fields {
+                    field.error = field.setting.set_text(&mut updated, &field.text).err();
+                }
+                if self.fields.iter().all(|field| field.error.is_none()) {
+                    *settings = updated;
+                    return FinderEvent::Exit;
+                }
+            }
+            KeyCode::Down | KeyCode::Tab if len > 0 => self.selected = (self.selected + 1) % len,
+            KeyCode::Up | KeyCode::BackTab if len > 0 => self.selected = (self.selected + len - 1) % len,
+            KeyCode::Backspace => {
+                if let Some(field) = self.fields.get_mut(self.selected) {
+                    field.text.pop();
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(field) = self.fields.get_mut(self.selected) {
+                    field.text.push(c);
+                }
+            }
+            _ => {}
+        }
+        FinderEvent::None
+    }
+
+    pub fn draw<B: tui::backend::Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let Some(entry) = self.entry else { return };
+        let mut lines = Vec::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            let hint = if field.setting.kind == SettingKind::List { " (separated by commas)" } else { "" };
+            lines.push(Line::from(Span::styled(format!("{}{}", field.setting.label, hint), Style::default().add_modifier(Modifier::BOLD))));
+            let (value, style) = if i == self.selected {
+                (format!("> {}_", field.text), theme::get().input)
+            } else {
+                (format!("  {}", field.text), Style::default())
+            };
+            lines.push(Line::from(Span::styled(value, style)));
+            if let Some(error) = &field.error {
+                lines.push(Line::from(Span::styled(format!("  {}", error), theme::get().error)));
+            }
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from("Enter: save  Up/Down: next setting  Esc: cancel"));
+
+        let width = 64.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let dialog = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        f.render_widget(Clear, dialog);
+        let paragraph = Paragraph::new(lines)
+            .wrap(tui::widgets::Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(format!("Settings of {}", entry.name)));
+        f.render_widget(paragraph, dialog);
+    }
+}
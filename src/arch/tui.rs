@@ -1,74 +1,201 @@
 use crossterm::{
-    event::{self, Event, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
-    execute,
+    event::{self, Event, KeyCode, MouseButton, MouseEvent, MouseEventKind},
 };
 use tui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
-    text::{Span, Spans},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Clear as ClearWidget, List, ListItem, ListState, Paragraph},
+    text::{Line, Span},
     Terminal,
 };
-use std::io;
+use std::cell::Cell;
+use std::io::{self, Stdout};
+use std::time::Duration;
+use crate::arch::dashboard::{Dashboard, DashboardEvent};
+use crate::arch::duplicate_finder::DuplicateFinder;
+use crate::arch::file_finder::{FileFinder, FinderEvent};
+use crate::arch::history_view::HistoryView;
 use crate::arch::operations::ArchOperation;
-use crate::operation_descriptions::get_description;
+use crate::arch::pacman_lock::{self, LockState};
+use crate::arch::prerequisites::{self, Unmet};
+use crate::arch::profile_picker::{PickerEvent, ProfilePicker};
+use crate::arch::progress::ProgressView;
+use crate::arch::quarantine_view::QuarantineView;
+use crate::arch::registry::{self, Action, Availability, Category, Entry, Tool};
+use crate::arch::schedule_view::ScheduleView;
+use crate::arch::settings_editor::SettingsEditor;
+use crate::config::{self, Config};
+use crate::execution::OperationError;
+use crate::filesystem::format_size;
+use crate::privilege;
+use crate::terminal::{self, TerminalGuard};
+use crate::trash::{self, TrashSummary};
+use crate::theme;
 
 #[derive(Clone)]
 struct MenuItem {
-    name: String,
-    is_category: bool,
+    label: &'static str,
+    category: Category,
+    entry: Option<&'static Entry>, // None for category headers
+    unmet: Option<Unmet>,          // Why the entry can't be used on this system
     selected: bool,
-    indent_level: usize,
+}
+
+impl MenuItem {
+    fn is_category(&self) -> bool {
+        self.entry.is_none()
+    }
+
+    fn is_selectable(&self) -> bool {
+        self.entry.is_some_and(Entry::is_runnable) && self.unmet.is_none()
+    }
+}
+
+// What the event loop has to do after a key press, for things that take the terminal over or end the TUI
+#[derive(Debug, PartialEq)]
+enum Effect {
+    None,
+    Quit,
+    InstallMissing,  // Hand the terminal to pacman
+    StartExecution,  // Authenticate, then run the checked operations
+    RemoveStaleLock,
+    ApplySchedule,
 }
 
 enum Screen {
+    Dashboard,
     Selection,
     Confirmation,
+    FileFinder,
+    DuplicateFinder,
+    Quarantine,
+    History,
+    Profiles,
+    Schedule,
+    Settings,
+    Progress,
 }
 
 pub struct ArchTui {
     items: Vec<MenuItem>,
-    state: ListState,
+    state: ListState,             // Indexes the visible rows, not items
+    filter: String,               // Search typed after /
+    searching: bool,              // Whether keys go to the search
+    collapsed: Vec<Category>,
+    list_view: Cell<(Rect, usize)>, // Where the operation list was last drawn and its first row, for mouse clicks
+    show_help: bool,
     current_screen: Screen,
+    trash_summary: TrashSummary,
+    dashboard: Dashboard,
+    file_finder: FileFinder,
+    duplicate_finder: DuplicateFinder,
+    quarantine_view: QuarantineView,
+    history_view: HistoryView,
+    profile_picker: ProfilePicker,
+    schedule_view: ScheduleView,
+    settings: Config, // The config, with the settings changed in this session
+    settings_editor: SettingsEditor,
+    pacman_lock: LockState,
+    waiting_for_lock: bool,
+    lock_error: Option<String>,
+    removal_plans: Vec<(&'static str, Result<Vec<String>, OperationError>)>, // By operation id, for the confirmation screen
+    install_error: Option<String>,
+    progress: Option<ProgressView>,
 }
 
 impl ArchTui {
     pub fn new() -> ArchTui {
-        let items = vec![
-            MenuItem { name: "Package management".to_string(), is_category: true, selected: false, indent_level: 0 },
-            MenuItem { name: "Clean cache".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Remove orphaned packages".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Manual unused package removal (coming soon)".to_string(), is_category: false, selected: false, indent_level: 1 },
-            
-            MenuItem { name: "Flatpack management".to_string(), is_category: true, selected: false, indent_level: 0 },
-            MenuItem { name: "Repair libraries".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Remove unused libraries".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Manual unused flatpak removal (coming soon)".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Change installation directory (coming soon)".to_string(), is_category: false, selected: false, indent_level: 1 },
-            
-            MenuItem { name: "Cache and logs".to_string(), is_category: true, selected: false, indent_level: 0 },
-            MenuItem { name: "Clear systemd journal".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Clean general logs (deprecated)".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Clean user cache".to_string(), is_category: false, selected: false, indent_level: 1 },
-            
-            MenuItem { name: "Config".to_string(), is_category: true, selected: false, indent_level: 0 },
-            MenuItem { name: "pac* file management".to_string(), is_category: false, selected: false, indent_level: 1 },
-            MenuItem { name: "Manual orphaned config removal (coming soon)".to_string(), is_category: false, selected: false, indent_level: 1 },
-        ];
-        
+        let mut tui = Self::unprobed(TrashSummary::default());
+        tui.refresh_trash_summary();
+        tui.dashboard.refresh();
+        tui.probe();
+        tui.select_operations(&config::get().default_selection);
+        tui
+    }
+
+    // The menu before anything is read from the system, which is also where the tests start from
+    fn unprobed(trash_summary: TrashSummary) -> ArchTui {
+        let mut items = Vec::new();
+        for category in Category::MENU {
+            items.push(MenuItem { label: category.label(), category, entry: None, unmet: None, selected: false });
+            for entry in registry::in_category(category) {
+                items.push(MenuItem { label: entry.name, category, entry: Some(entry), unmet: None, selected: false });
+            }
+        }
+
         let mut state = ListState::default();
         state.select(Some(0));
         ArchTui { 
             items, 
             state, 
-            current_screen: Screen::Selection,
+            filter: String::new(),
+            searching: false,
+            collapsed: Vec::new(),
+            list_view: Cell::new((Rect::default(), 0)),
+            show_help: false,
+            current_screen: Screen::Dashboard,
+            trash_summary,
+            dashboard: Dashboard::new(),
+            file_finder: FileFinder::new(),
+            duplicate_finder: DuplicateFinder::new(),
+            quarantine_view: QuarantineView::new(),
+            history_view: HistoryView::new(),
+            profile_picker: ProfilePicker::new(),
+            schedule_view: ScheduleView::new(),
+            settings: config::get().clone(),
+            settings_editor: SettingsEditor::new(),
+            pacman_lock: LockState::Free,
+            waiting_for_lock: false,
+            lock_error: None,
+            removal_plans: Vec::new(),
+            install_error: None,
+            progress: None,
         }
     }
 
+    // Finds out which entries can't be used on this system, and unchecks them
+    fn probe(&mut self) {
+        for item in self.items.iter_mut() {
+            item.unmet = item.entry.filter(|entry| entry.availability == Availability::Available).and_then(Entry::probe);
+            if item.unmet.is_some() {
+                item.selected = false;
+            }
+        }
+    }
+
+    // Installs the package that would make the highlighted entry usable. pacman asks for confirmation and
+    // maybe a password, which needs the terminal back.
+    fn install_missing(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<(), io::Error> {
+        let Some(package) = self.selected_index().and_then(|i| self.items[i].unmet.as_ref()?.package) else {
+            return Ok(());
+        };
+        self.install_error = terminal::suspend(terminal, || prerequisites::install(package))?.err();
+        self.probe();
+        Ok(())
+    }
+
+    // Checks the items whose operation ids are listed
+    fn select_operations(&mut self, ids: &[String]) {
+        for item in self.items.iter_mut().filter(|item| item.is_selectable()) {
+            if item.entry.is_some_and(|entry| ids.iter().any(|id| id == entry.id)) {
+                item.selected = true;
+            }
+        }
+    }
+
+    // Ids of the checked items, eg. to save them in a profile
+    fn selected_operation_ids(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .filter(|item| item.selected)
+            .filter_map(|item| item.entry)
+            .map(|entry| entry.id.to_string())
+            .collect()
+    }
+
     fn toggle_category(&mut self, category_index: usize) {
-        if !self.items[category_index].is_category {
+        if !self.items[category_index].is_category() {
             return;
         }
 
@@ -76,50 +203,334 @@ impl ArchTui {
         let new_state = !self.items[category_index].selected;
         self.items[category_index].selected = new_state;
 
-        // Find the range of items in this category
-        let mut end_index = category_index + 1;
-        while end_index < self.items.len() && !self.items[end_index].is_category {
-            self.items[end_index].selected = new_state;
-            end_index += 1;
+        // While searching only the matching operations are toggled. Tools that open their own screen and
+        // unavailable operations can't be selected.
+        let category = self.items[category_index].category;
+        let toggled: Vec<usize> = (0..self.items.len())
+            .filter(|&i| {
+                let item = &self.items[i];
+                !item.is_category() && item.category == category && item.is_selectable() && self.matches(item)
+            })
+            .collect();
+        for i in toggled {
+            self.items[i].selected = new_state;
         }
     }
 
-    fn get_operation_for_item(&self, item_name: &str) -> Option<ArchOperation> {
-        match item_name {
-            "Clean cache" => Some(ArchOperation::CleanCache),
-            "Remove orphaned packages" => Some(ArchOperation::RemoveOrphaned),
-            "Manual unused package removal" => Some(ArchOperation::ManualPackageRemoval),
-            "Repair libraries" => Some(ArchOperation::RepairFlatpak),
-            "Remove unused libraries" => Some(ArchOperation::RemoveUnusedFlatpak),
-            "Manual unused flatpak removal" => Some(ArchOperation::ManualFlatpakRemoval),
-            "Change installation directory" => Some(ArchOperation::ChangeFlatpakDir),
-            "Clear systemd journal" => Some(ArchOperation::ClearSystemdJournal),
-            "Clean general logs" => Some(ArchOperation::CleanGeneralLogs),
-            "Clean user cache" => Some(ArchOperation::CleanUserCache),
-            "pac* file management" => Some(ArchOperation::ManagePacFiles),
-            "Manual orphaned config removal" => Some(ArchOperation::RemoveOrphanedConfigs),
-            _ => None,
+    // Whether an entry's name or description contains the search, ignoring case
+    fn matches(&self, item: &MenuItem) -> bool {
+        let Some(entry) = item.entry else { return true };
+        let query = self.filter.to_lowercase();
+        entry.name.to_lowercase().contains(&query) || entry.description.to_lowercase().contains(&query)
+    }
+
+    // Indices of the items shown in the list. While searching that is the matching entries under their
+    // category, otherwise everything but the entries of collapsed categories.
+    fn visible(&self) -> Vec<usize> {
+        (0..self.items.len())
+            .filter(|&i| {
+                let item = &self.items[i];
+                match (item.is_category(), self.filter.is_empty()) {
+                    (true, true) => true,
+                    (true, false) => self.items.iter().any(|other| !other.is_category() && other.category == item.category && self.matches(other)),
+                    (false, true) => !self.collapsed.contains(&item.category),
+                    (false, false) => self.matches(item),
+                }
+            })
+            .collect()
+    }
+
+    // Index in items of the highlighted row
+    fn selected_index(&self) -> Option<usize> {
+        self.state.selected().and_then(|row| self.visible().get(row).copied())
+    }
+
+    // Highlights the row of the given item, or the first operation shown if it is hidden now
+    fn highlight(&mut self, index: Option<usize>) {
+        let visible = self.visible();
+        let row = index
+            .and_then(|index| visible.iter().position(|&i| i == index))
+            .or_else(|| visible.iter().position(|&i| !self.items[i].is_category()))
+            .or(if visible.is_empty() { None } else { Some(0) });
+        self.state.select(row);
+    }
+
+    // Highlights a row, clamped to the rows shown
+    fn select_row(&mut self, row: usize) {
+        let len = self.visible().len();
+        self.state.select(if len == 0 { None } else { Some(row.min(len - 1)) });
+    }
+
+    // Rows of the operation list that fit on the screen
+    fn page_size(&self) -> usize {
+        (self.list_view.get().0.height as usize).saturating_sub(2).max(1)
+    }
+
+    fn move_highlight(&mut self, forward: bool) {
+        let len = self.visible().len();
+        if len == 0 {
+            return;
+        }
+        let row = match self.state.selected() {
+            Some(row) if forward => (row + 1) % len,
+            Some(0) => len - 1,
+            Some(row) => row - 1,
+            None => 0,
+        };
+        self.state.select(Some(row));
+    }
+
+    // Rows of the category headers shown
+    fn header_rows(&self) -> Vec<usize> {
+        self.visible().iter().enumerate().filter(|(_, &i)| self.items[i].is_category()).map(|(row, _)| row).collect()
+    }
+
+    // Jumps to the n-th category shown, counting from 0
+    fn jump_to_category(&mut self, n: usize) {
+        if let Some(&row) = self.header_rows().get(n) {
+            self.state.select(Some(row));
+        }
+    }
+
+    // Jumps to the next (or previous) category header, wrapping around
+    fn jump_past_category(&mut self, forward: bool) {
+        let headers = self.header_rows();
+        let current = self.state.selected().unwrap_or(0);
+        let row = if forward {
+            headers.iter().find(|&&row| row > current).or(headers.first())
+        } else {
+            headers.iter().rev().find(|&&row| row < current).or(headers.last())
+        };
+        if let Some(&row) = row {
+            self.state.select(Some(row));
+        }
+    }
+
+    // Folds (or unfolds) the category of the highlighted item, which stays checked or unchecked as it was
+    fn set_collapsed(&mut self, collapse: bool) {
+        let Some(index) = self.selected_index() else { return };
+        let header = (0..=index).rev().find(|&i| self.items[i].is_category()).unwrap_or(0);
+        let category = self.items[header].category;
+        self.collapsed.retain(|collapsed| *collapsed != category);
+        if collapse {
+            self.collapsed.push(category);
+            self.highlight(Some(header));
+        } else {
+            self.highlight(Some(index));
+        }
+    }
+
+    // The settings of an operation as they are now, eg. "Package versions to keep: 3"
+    fn settings_summary(&self, entry: &Entry) -> Vec<String> {
+        entry
+            .settings
+            .iter()
+            .map(|setting| {
+                let value = setting.get(&self.settings);
+                format!("{}: {}", setting.label, if value.is_empty() { "none" } else { &value })
+            })
+            .collect()
+    }
+
+    fn edit_settings(&mut self) {
+        let Some(entry) = self.selected_index().and_then(|i| self.items[i].entry) else { return };
+        if entry.is_runnable() && !entry.settings.is_empty() {
+            self.settings_editor.open(entry, &self.settings);
+            self.current_screen = Screen::Settings;
         }
     }
 
-    fn execute_selected_operations(&self) -> Vec<Result<(), String>> {
-        let mut results = Vec::new();
-        
-        println!("\nSelected operations to execute:");
-        for item in &self.items {
-            if !item.is_category && item.selected {
-                println!("• {}", item.name);
-                if let Some(operation) = self.get_operation_for_item(&item.name) {
-                    results.push(operation.execute());
+    // Checks or unchecks the highlighted row, without opening tools
+    fn toggle_highlighted(&mut self) {
+        let Some(i) = self.selected_index() else { return };
+        if self.items[i].is_category() {
+            self.toggle_category(i);
+        } else if self.items[i].is_selectable() {
+            self.items[i].selected = !self.items[i].selected;
+        }
+    }
+
+    // Enter checks an operation, toggles a whole category or opens a tool
+    fn activate_highlighted(&mut self) {
+        let Some(i) = self.selected_index() else { return };
+        match Self::screen_for_item(&self.items[i]) {
+            Some(screen) => self.open_screen(screen),
+            None => self.toggle_highlighted(),
+        }
+    }
+
+    // Checks every operation shown, leaving the hidden ones as they were, or unchecks everything
+    fn select_all(&mut self, selected: bool) {
+        let shown = self.visible();
+        for (i, item) in self.items.iter_mut().enumerate() {
+            if !selected {
+                item.selected = false;
+            } else if shown.contains(&i) {
+                item.selected = item.is_category() || item.is_selectable();
+            }
+        }
+    }
+
+    // Scrolling stands in for Up and Down on every screen. In the operation list, a click highlights a row and
+    // a click on the highlighted row acts like Enter.
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Option<KeyCode> {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => Some(KeyCode::Down),
+            MouseEventKind::ScrollUp => Some(KeyCode::Up),
+            MouseEventKind::Down(MouseButton::Left) if matches!(self.current_screen, Screen::Selection) && !self.show_help => {
+                let (area, offset) = self.list_view.get();
+                let inside = mouse.column > area.x
+                    && mouse.column < area.right().saturating_sub(1)
+                    && mouse.row > area.y
+                    && mouse.row < area.bottom().saturating_sub(1);
+                if !inside {
+                    return None;
                 }
+                let row = offset + (mouse.row - area.y - 1) as usize;
+                if row >= self.visible().len() {
+                    None
+                } else if self.state.selected() == Some(row) {
+                    Some(KeyCode::Enter)
+                } else {
+                    self.state.select(Some(row));
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Typing while searching narrows the list down as it goes. Enter keeps the search, Esc drops it.
+    fn handle_search_key(&mut self, key: KeyCode) {
+        let highlighted = self.selected_index();
+        match key {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.filter.clear();
             }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => return,
+        }
+        self.highlight(highlighted);
+    }
+
+    // Interactive tools open a screen of their own instead of being selected
+    fn screen_for_item(item: &MenuItem) -> Option<Screen> {
+        let Action::Open(tool) = item.entry?.action else { return None };
+        if item.unmet.is_some() {
+            return None;
+        }
+        Some(match tool {
+            Tool::LargeFiles => Screen::FileFinder,
+            Tool::DuplicateFiles => Screen::DuplicateFinder,
+            Tool::Quarantine => Screen::Quarantine,
+            Tool::DiskUsage => Screen::Dashboard,
+            Tool::History => Screen::History,
+            Tool::Profiles => Screen::Profiles,
+            Tool::Schedule => Screen::Schedule,
+        })
+    }
+
+    fn open_screen(&mut self, screen: Screen) {
+        match screen {
+            Screen::Dashboard => self.dashboard.refresh(),
+            Screen::Quarantine => self.quarantine_view.reload(),
+            Screen::History => self.history_view.reload(),
+            Screen::Profiles => self.profile_picker.reload(),
+            Screen::Schedule => self.schedule_view.reload(),
+            _ => {}
         }
-        
-        if results.is_empty() {
-            println!("No operations selected!");
+        self.current_screen = screen;
+    }
+
+    fn has_pending_operations(&self) -> bool {
+        self.items.iter().any(|item| item.selected && !item.is_category())
+            || self.file_finder.has_marks()
+            || self.duplicate_finder.has_marks()
+    }
+
+    fn selected_operations(&self) -> Vec<ArchOperation> {
+        let mut operations: Vec<ArchOperation> = self.items
+            .iter()
+            .filter(|item| item.selected)
+            .filter_map(|item| item.entry?.build(&self.settings))
+            .collect();
+        operations.extend(self.file_finder.operations());
+        operations.extend(self.duplicate_finder.operations());
+        operations
+    }
+
+    fn enter_confirmation(&mut self) {
+        let operations = self.selected_operations();
+        self.removal_plans = operations
+            .iter()
+            .filter_map(|operation| Some((operation.id(), operation.removal_plan()?)))
+            .collect();
+        self.pacman_lock = if operations.iter().any(ArchOperation::uses_pacman_db) {
+            pacman_lock::check()
+        } else {
+            LockState::Free
+        };
+        self.lock_error = None;
+        self.current_screen = Screen::Confirmation;
+    }
+
+    // Starts the selected operations in the background and switches to the screen following them
+    fn start_execution(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<(), io::Error> {
+        let operations = self.selected_operations();
+
+        // Authenticate up front, while the terminal is out of raw mode and a password prompt can be seen
+        let mut root_error = None;
+        let keep_alive = if operations.iter().any(ArchOperation::needs_root) {
+            terminal::suspend(terminal, || {
+                privilege::begin_batch(config::get().escalation).unwrap_or_else(|e| {
+                    root_error = Some(OperationError::PermissionDenied(e));
+                    None
+                })
+            })?
+        } else {
+            None
+        };
+
+        self.progress = Some(ProgressView::start(operations, root_error, keep_alive));
+        self.current_screen = Screen::Progress;
+        Ok(())
+    }
+
+    // Back to the menu after a run, with nothing selected: marked files are gone, and the rest was just done
+    fn finish_execution(&mut self) {
+        self.progress = None;
+        for item in &mut self.items {
+            item.selected = false;
         }
-        
-        results
+        self.file_finder = FileFinder::new();
+        self.duplicate_finder = DuplicateFinder::new();
+        self.refresh_trash_summary();
+        self.current_screen = Screen::Selection;
+    }
+
+    // The summary counts what Empty trash would remove with the age it is set to for this session
+    fn refresh_trash_summary(&mut self) {
+        self.trash_summary = trash::summarize(self.settings.trash_min_age_days);
+    }
+
+    fn trash_summary_lines(&self) -> Vec<Line<'static>> {
+        let summary = &self.trash_summary;
+        vec![
+            Line::from(""),
+            Line::from(format!("Trash directories found: {}", summary.trash_dirs)),
+            Line::from(format!("Items in trash: {} ({})", summary.total_items, format_size(summary.total_bytes))),
+            Line::from(format!(
+                "Older than {} days: {} ({})",
+                self.settings.trash_min_age_days,
+                summary.expired_items,
+                format_size(summary.expired_bytes)
+            )),
+        ]
     }
 
     fn draw_confirmation_screen<B: tui::backend::Backend>(
@@ -145,14 +556,58 @@ impl ArchTui {
 
         // Selected operations
         let selected_ops: Vec<ListItem> = self.items.iter()
-            .filter(|item| !item.is_category && item.selected)
-            .map(|item| ListItem::new(format!("• {}", item.name)))
+            .filter(|item| item.selected && !item.is_category())
+            .map(|item| {
+                let settings = item.entry.map(|entry| self.settings_summary(entry)).unwrap_or_default();
+                let mut lines = vec![Line::from(if settings.is_empty() {
+                    format!("• {}", item.label)
+                } else {
+                    format!("• {} ({})", item.label, settings.join(", "))
+                })];
+                let plan = self.removal_plans.iter().find(|(id, _)| item.entry.is_some_and(|entry| entry.id == *id));
+                match plan.map(|(_, plan)| plan) {
+                    Some(Ok(packages)) if packages.is_empty() => lines.push(Line::from("    Nothing to remove")),
+                    Some(Ok(packages)) => {
+                        lines.push(Line::from(format!("    Removes {} packages:", packages.len())));
+                        let width = chunks[1].width.saturating_sub(8) as usize;
+                        lines.extend(wrap_words(packages, width).into_iter().map(|line| Line::from(format!("      {}", line))));
+                    }
+                    Some(Err(e)) => lines.push(Line::from(Span::styled(format!("    Could not list what it removes: {}", e), theme::get().error))),
+                    None => {}
+                }
+                ListItem::new(lines)
+            })
+            .chain(self.file_finder.confirmation_lines().into_iter().map(|line| ListItem::new(format!("• {}", line))))
+            .chain(self.duplicate_finder.confirmation_lines().into_iter().map(|line| ListItem::new(format!("• {}", line))))
             .collect();
 
+        // A locked pacman database takes some room from the list for a warning
+        let list_area = if matches!(self.pacman_lock, LockState::Free) {
+            chunks[1]
+        } else {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(5)].as_ref())
+                .split(chunks[1]);
+            let mut warning = vec![Line::from(self.pacman_lock.describe())];
+            if self.waiting_for_lock {
+                warning.push(Line::from("Waiting for the lock to be released, the operations will start on their own..."));
+            }
+            if let Some(e) = &self.lock_error {
+                warning.push(Line::from(format!("Could not remove the lock: {}", e)));
+            }
+            let warning = Paragraph::new(warning)
+                .style(theme::get().warning)
+                .wrap(tui::widgets::Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title("Pacman database locked"));
+            f.render_widget(warning, split[1]);
+            split[0]
+        };
+
         let operations_list = List::new(selected_ops)
             .block(Block::default().borders(Borders::ALL).title("Selected Operations"))
             .style(Style::default());
-        f.render_widget(operations_list, chunks[1]);
+        f.render_widget(operations_list, list_area);
 
         // Buttons
         let button_layout = Layout::default()
@@ -163,17 +618,32 @@ impl ArchTui {
             ].as_ref())
             .split(chunks[2]);
 
-        let confirm = Paragraph::new(Spans::from(vec![
-            Span::styled("Press ", Style::default()),
-            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-            Span::styled(" to execute", Style::default()),
-        ]))
+        let confirm_text = match self.pacman_lock {
+            LockState::Free => vec![
+                Span::styled("Press ", Style::default()),
+                Span::styled("Enter", theme::get().ok.add_modifier(Modifier::BOLD)),
+                Span::styled(" to execute", Style::default()),
+            ],
+            LockState::Held(_) => vec![
+                Span::styled("Press ", Style::default()),
+                Span::styled("w", theme::get().ok.add_modifier(Modifier::BOLD)),
+                Span::styled(" to wait for the lock", Style::default()),
+            ],
+            LockState::Stale => vec![
+                Span::styled("Press ", Style::default()),
+                Span::styled("w", theme::get().ok.add_modifier(Modifier::BOLD)),
+                Span::styled(" to wait or ", Style::default()),
+                Span::styled("r", theme::get().warning.add_modifier(Modifier::BOLD)),
+                Span::styled(" to remove the stale lock", Style::default()),
+            ],
+        };
+        let confirm = Paragraph::new(Line::from(confirm_text))
         .alignment(tui::layout::Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
 
-        let cancel = Paragraph::new(Spans::from(vec![
+        let cancel = Paragraph::new(Line::from(vec![
             Span::styled("Press ", Style::default()),
-            Span::styled("Esc", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled("Esc", theme::get().error.add_modifier(Modifier::BOLD)),
             Span::styled(" to cancel", Style::default()),
         ]))
         .alignment(tui::layout::Alignment::Center)
@@ -183,6 +653,47 @@ impl ArchTui {
         f.render_widget(cancel, button_layout[1]);
     }
 
+    // Every key of the operation list, over whatever is on the screen
+    fn draw_help<B: tui::backend::Backend>(f: &mut tui::Frame<B>, size: Rect) {
+        const BINDINGS: [(&str, &str); 18] = [
+            ("Up/k, Down/j", "Move the highlight"),
+            ("g/Home, G/End", "First or last row"),
+            ("PageUp, PageDown", "Move a page"),
+            ("Enter", "Check an operation or category, or open a tool"),
+            ("Space", "Check or uncheck"),
+            ("a, n", "Check all operations shown, or none"),
+            ("Left, Right", "Fold or unfold a category"),
+            ("Tab, Shift+Tab, 1-7", "Jump between categories"),
+            ("/", "Search, Esc drops the search"),
+            ("e", "Change the settings of an operation"),
+            ("i", "Install what an unavailable operation needs"),
+            ("f", "Find large files"),
+            ("p", "Profiles"),
+            ("c", "Confirm the checked operations"),
+            ("q", "Quit"),
+            ("Click", "Highlight a row, again to check it"),
+            ("Scroll", "Move the highlight"),
+            ("?", "This help, any key closes it"),
+        ];
+        let lines: Vec<Line> = BINDINGS
+            .iter()
+            .map(|(keys, action)| {
+                Line::from(vec![
+                    Span::styled(format!("{:<22}", keys), theme::get().key),
+                    Span::raw(*action),
+                ])
+            })
+            .collect();
+        let width = 72.min(size.width);
+        let height = (BINDINGS.len() as u16 + 2).min(size.height);
+        let area = Rect::new(size.x + (size.width - width) / 2, size.y + (size.height - height) / 2, width, height);
+        f.render_widget(ClearWidget, area);
+        let help = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Keys"))
+            .alignment(Alignment::Left);
+        f.render_widget(help, area);
+    }
+
     fn draw_selection_screen<B: tui::backend::Backend>(
         &self,
         f: &mut tui::Frame<B>,
@@ -197,20 +708,37 @@ impl ArchTui {
             .split(size);
 
         // Description panel on the left
-        let description = if let Some(selected) = self.state.selected() {
-            let item = &self.items[selected];
-            if !item.is_category {
-                let desc = get_description(&item.name);
-                vec![
-                    Spans::from(Span::styled(desc.title, Style::default().add_modifier(Modifier::BOLD))),
-                    Spans::from(""),
-                    Spans::from(desc.description),
-                ]
-            } else {
-                vec![Spans::from("Select an operation to see its description")]
+        let selected = self.selected_index().map(|i| &self.items[i]);
+        let description = match selected.and_then(|item| Some((item.entry?, &item.unmet))) {
+            Some((entry, unmet)) => {
+                let mut lines = vec![
+                    Line::from(Span::styled(format!("{}{}", entry.name, entry.availability.suffix()), Style::default().add_modifier(Modifier::BOLD))),
+                    Line::from(""),
+                    Line::from(entry.description),
+                ];
+                let settings = self.settings_summary(entry);
+                if !settings.is_empty() {
+                    lines.push(Line::from(""));
+                    lines.extend(settings.into_iter().map(Line::from));
+                    lines.push(Line::from("Press e to change these for this run"));
+                }
+                if let Some(unmet) = unmet {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(Span::styled(format!("Unavailable: {}", unmet.error), theme::get().error)));
+                    if let Some(package) = unmet.package {
+                        lines.push(Line::from(format!("Press i to install {}", package)));
+                    }
+                    if let Some(error) = &self.install_error {
+                        lines.push(Line::from(Span::styled(error.clone(), theme::get().error)));
+                    }
+                }
+                if entry.id == "empty-trash" {
+                    lines.extend(self.trash_summary_lines());
+                }
+                lines
             }
-        } else {
-            vec![Spans::from("Select an operation to see its description")]
+            None if self.visible().is_empty() => vec![Line::from("No operation matches the search")],
+            None => vec![Line::from("Select an operation to see its description")],
         };
 
         let description_widget = Paragraph::new(description)
@@ -227,51 +755,83 @@ impl ArchTui {
             ].as_ref())
             .split(main_chunks[1]);
 
-        let items: Vec<ListItem> = self.items
-            .iter()
+        let items: Vec<ListItem> = self.visible()
+            .into_iter()
+            .map(|i| &self.items[i])
             .map(|item| {
-                let indent = "  ".repeat(item.indent_level);
-                let prefix = if item.is_category {
-                    format!("{}{} ", indent, if item.selected { "[x]" } else { "[ ]" })
-                } else {
-                    format!("{}{} ", indent, if item.selected { "[x]" } else { "[ ]" })
+                let Some(entry) = item.entry else {
+                    let checkbox = if item.selected { "[x]" } else { "[ ]" };
+                    let fold = if self.filter.is_empty() && self.collapsed.contains(&item.category) { "+" } else { "-" };
+                    let style = theme::get().heading;
+                    return ListItem::new(format!("{} {} {}", fold, checkbox, item.label)).style(style);
                 };
-                let style = if item.is_category {
-                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+                let (checkbox, style) = if item.unmet.is_some() {
+                    ("[-]", theme::get().dimmed)
+                } else if Self::screen_for_item(item).is_some() {
+                    ("[>]", Style::default())
+                } else if !item.is_selectable() {
+                    ("[-]", theme::get().dimmed)
+                } else if item.selected {
+                    ("[x]", Style::default())
                 } else {
-                    Style::default()
+                    ("[ ]", Style::default())
                 };
-                ListItem::new(format!("{}{}", prefix, item.name)).style(style)
+                ListItem::new(format!("    {} {}{}", checkbox, item.label, entry.availability.suffix())).style(style)
             })
             .collect();
 
+        let title = if self.searching || !self.filter.is_empty() {
+            format!("Search: {}{}", self.filter, if self.searching { "_" } else { "" })
+        } else {
+            String::from("Select operations to perform (/ to search, ? for keys)")
+        };
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Select operations to perform"))
-            .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(theme::get().highlight)
             .highlight_symbol(">> ");
 
-        f.render_stateful_widget(list, right_chunks[0], &mut self.state.clone());
+        let mut state = self.state.clone();
+        f.render_stateful_widget(list, right_chunks[0], &mut state);
+        self.list_view.set((right_chunks[0], state.offset()));
 
         // Bottom buttons
         let button_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Percentage(50),
-                Constraint::Percentage(50),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
             ].as_ref())
             .split(right_chunks[1]);
 
-        let confirm_text = Spans::from(vec![
+        let finder_text = Line::from(vec![
             Span::styled("Press ", Style::default()),
-            Span::styled("c", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled("f", theme::get().key),
+            Span::styled(" to find large files", Style::default()),
+        ]);
+        let profiles_text = Line::from(vec![
+            Span::styled("Press ", Style::default()),
+            Span::styled("p", theme::get().notice.add_modifier(Modifier::BOLD)),
+            Span::styled(" for profiles", Style::default()),
+        ]);
+        let confirm_text = Line::from(vec![
+            Span::styled("Press ", Style::default()),
+            Span::styled("c", theme::get().ok.add_modifier(Modifier::BOLD)),
             Span::styled(" to confirm", Style::default()),
         ]);
-        let exit_text = Spans::from(vec![
+        let exit_text = Line::from(vec![
             Span::styled("Press ", Style::default()),
-            Span::styled("q", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled("q", theme::get().error.add_modifier(Modifier::BOLD)),
             Span::styled(" to exit", Style::default()),
         ]);
 
+        let finder_block = Paragraph::new(finder_text)
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(tui::layout::Alignment::Center);
+        let profiles_block = Paragraph::new(profiles_text)
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(tui::layout::Alignment::Center);
         let confirm_block = Paragraph::new(confirm_text)
             .block(Block::default().borders(Borders::ALL))
             .alignment(tui::layout::Alignment::Center);
@@ -279,110 +839,261 @@ impl ArchTui {
             .block(Block::default().borders(Borders::ALL))
             .alignment(tui::layout::Alignment::Center);
 
-        f.render_widget(confirm_block, button_layout[0]);
-        f.render_widget(exit_block, button_layout[1]);
+        f.render_widget(finder_block, button_layout[0]);
+        f.render_widget(profiles_block, button_layout[1]);
+        f.render_widget(confirm_block, button_layout[2]);
+        f.render_widget(exit_block, button_layout[3]);
     }
 
-    pub fn run(&mut self) -> Result<(), io::Error> {
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, Clear(ClearType::All))?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+    // Draws the current screen, with the help on top when it is open
+    fn draw<B: tui::backend::Backend>(&self, f: &mut tui::Frame<B>) {
+        let size = f.size();
+        match self.current_screen {
+            Screen::Dashboard => self.dashboard.draw(f, size),
+            Screen::Selection => self.draw_selection_screen(f, size),
+            Screen::Confirmation => self.draw_confirmation_screen(f, size),
+            Screen::FileFinder => self.file_finder.draw(f, size),
+            Screen::DuplicateFinder => self.duplicate_finder.draw(f, size),
+            Screen::Quarantine => self.quarantine_view.draw(f, size),
+            Screen::History => self.history_view.draw(f, size),
+            Screen::Profiles => self.profile_picker.draw(f, size),
+            Screen::Schedule => self.schedule_view.draw(f, size),
+            Screen::Settings => {
+                self.draw_selection_screen(f, size);
+                self.settings_editor.draw(f, size);
+            }
+            Screen::Progress => {
+                if let Some(progress) = &self.progress {
+                    progress.draw(f, size);
+                }
+            }
+        }
+        if self.show_help {
+            Self::draw_help(f, size);
+        }
+    }
 
-        loop {
-            terminal.draw(|f| {
-                let size = f.size();
-                match self.current_screen {
-                    Screen::Selection => self.draw_selection_screen(f, size),
-                    Screen::Confirmation => self.draw_confirmation_screen(f, size),
+    // Updates the state for a key press. Anything that needs the terminal is left to the event loop.
+    fn handle_key(&mut self, key: KeyCode) -> Effect {
+        // Any key closes the help
+        if self.show_help {
+            self.show_help = false;
+            return Effect::None;
+        }
+
+        match self.current_screen {
+            Screen::Dashboard => {
+                match self.dashboard.handle_key(key) {
+                    DashboardEvent::Quit => return Effect::Quit,
+                    DashboardEvent::Continue => self.current_screen = Screen::Selection,
+                    DashboardEvent::Apply(ids) => {
+                        self.select_operations(&ids);
+                        self.current_screen = Screen::Selection;
+                    }
+                    DashboardEvent::None => {}
                 }
-            })?;
-
-            if let Event::Key(key) = event::read()? {
-                match self.current_screen {
-                    Screen::Selection => {
-                        match key.code {
-                            KeyCode::Char('q') => {
-                                disable_raw_mode()?;
-                                break;
-                            }
-                            KeyCode::Char('c') => {
-                                // Check if any operations are selected
-                                if self.items.iter().any(|item| !item.is_category && item.selected) {
-                                    self.current_screen = Screen::Confirmation;
-                                }
-                            }
-                            KeyCode::Enter => {
-                                if let Some(i) = self.state.selected() {
-                                    if self.items[i].is_category {
-                                        self.toggle_category(i);
-                                    } else {
-                                        self.items[i].selected = !self.items[i].selected;
-                                    }
-                                }
-                            }
-                            KeyCode::Down => {
-                                let i = match self.state.selected() {
-                                    Some(i) => {
-                                        if i >= self.items.len() - 1 {
-                                            0
-                                        } else {
-                                            i + 1
-                                        }
-                                    }
-                                    None => 0,
-                                };
-                                self.state.select(Some(i));
-                            }
-                            KeyCode::Up => {
-                                let i = match self.state.selected() {
-                                    Some(i) => {
-                                        if i == 0 {
-                                            self.items.len() - 1
-                                        } else {
-                                            i - 1
-                                        }
-                                    }
-                                    None => 0,
-                                };
-                                self.state.select(Some(i));
-                            }
-                            _ => {}
-                        }
+            }
+            Screen::Selection if self.searching => self.handle_search_key(key),
+            Screen::Selection => {
+                match key {
+                    KeyCode::Char('q') => return Effect::Quit,
+                    // Only move on if any operations are selected
+                    KeyCode::Char('c') if self.has_pending_operations() => {
+                        self.enter_confirmation();
                     }
-                    Screen::Confirmation => {
-                        match key.code {
-                            KeyCode::Enter => {
-                                disable_raw_mode()?;
-                                execute!(io::stdout(), Clear(ClearType::All))?;
-                                let results = self.execute_selected_operations();
-                                if !results.is_empty() {
-                                    println!("\nExecution results:");
-                                    for result in results {
-                                        match result {
-                                            Ok(_) => (),
-                                            Err(e) => eprintln!("Error: {}", e),
-                                        }
-                                    }
-                                }
-                                break;
-                            }
-                            KeyCode::Esc => {
-                                self.current_screen = Screen::Selection;
-                            }
-                            _ => {}
+                    KeyCode::Char('f') => {
+                        self.current_screen = Screen::FileFinder;
+                    }
+                    KeyCode::Char('p') => self.open_screen(Screen::Profiles),
+                    KeyCode::Char('i') => return Effect::InstallMissing,
+                    KeyCode::Char('e') => self.edit_settings(),
+                    KeyCode::Char('/') => self.searching = true,
+                    KeyCode::Esc if !self.filter.is_empty() => self.handle_search_key(KeyCode::Esc),
+                    KeyCode::Left if self.filter.is_empty() => self.set_collapsed(true),
+                    KeyCode::Right if self.filter.is_empty() => self.set_collapsed(false),
+                    KeyCode::Tab => self.jump_past_category(true),
+                    KeyCode::BackTab => self.jump_past_category(false),
+                    KeyCode::Char(c @ '1'..='9') => self.jump_to_category(c as usize - '1' as usize),
+                    KeyCode::Char('?') => self.show_help = true,
+                    KeyCode::Enter => self.activate_highlighted(),
+                    KeyCode::Char(' ') => self.toggle_highlighted(),
+                    KeyCode::Char('a') => self.select_all(true),
+                    KeyCode::Char('n') => self.select_all(false),
+                    KeyCode::Down | KeyCode::Char('j') => self.move_highlight(true),
+                    KeyCode::Up | KeyCode::Char('k') => self.move_highlight(false),
+                    KeyCode::Home | KeyCode::Char('g') => self.select_row(0),
+                    KeyCode::End | KeyCode::Char('G') => self.select_row(usize::MAX),
+                    KeyCode::PageDown => self.select_row(self.state.selected().unwrap_or(0) + self.page_size()),
+                    KeyCode::PageUp => self.select_row(self.state.selected().unwrap_or(0).saturating_sub(self.page_size())),
+                    _ => {}
+                }
+            }
+            Screen::Confirmation => {
+                match key {
+                    KeyCode::Enter if matches!(self.pacman_lock, LockState::Free) => return Effect::StartExecution,
+                    KeyCode::Char('w') if !matches!(self.pacman_lock, LockState::Free) => {
+                        self.waiting_for_lock = true;
+                    }
+                    KeyCode::Char('r') if matches!(self.pacman_lock, LockState::Stale) => return Effect::RemoveStaleLock,
+                    KeyCode::Esc => {
+                        self.waiting_for_lock = false;
+                        self.current_screen = Screen::Selection;
+                    }
+                    _ => {}
+                }
+            }
+            Screen::FileFinder => {
+                match self.file_finder.handle_key(key) {
+                    FinderEvent::Exit => self.current_screen = Screen::Selection,
+                    FinderEvent::Confirm => self.enter_confirmation(),
+                    FinderEvent::None => {}
+                }
+            }
+            Screen::DuplicateFinder => {
+                match self.duplicate_finder.handle_key(key) {
+                    FinderEvent::Exit => self.current_screen = Screen::Selection,
+                    FinderEvent::Confirm => self.enter_confirmation(),
+                    FinderEvent::None => {}
+                }
+            }
+            Screen::Quarantine => {
+                if let FinderEvent::Exit = self.quarantine_view.handle_key(key) {
+                    self.current_screen = Screen::Selection;
+                }
+            }
+            Screen::History => {
+                if let FinderEvent::Exit = self.history_view.handle_key(key) {
+                    self.current_screen = Screen::Selection;
+                }
+            }
+            Screen::Profiles => {
+                match self.profile_picker.handle_key(key) {
+                    PickerEvent::Exit => self.current_screen = Screen::Selection,
+                    // A profile replaces whatever was checked before
+                    PickerEvent::Apply(ids) => {
+                        for item in self.items.iter_mut() {
+                            item.selected = false;
                         }
+                        self.select_operations(&ids);
+                        self.current_screen = Screen::Selection;
+                    }
+                    PickerEvent::Save(name) => {
+                        let ids = self.selected_operation_ids();
+                        let result = config::save_profile(&name, &ids);
+                        self.profile_picker.saved(name, ids, result);
+                    }
+                    PickerEvent::None => {}
+                }
+            }
+            Screen::Schedule => {
+                match self.schedule_view.handle_key(key) {
+                    FinderEvent::Exit => self.current_screen = Screen::Selection,
+                    FinderEvent::Confirm => return Effect::ApplySchedule,
+                    FinderEvent::None => {}
+                }
+            }
+            Screen::Settings => {
+                let min_age_days = self.settings.trash_min_age_days;
+                if let FinderEvent::Exit = self.settings_editor.handle_key(key, &mut self.settings) {
+                    if self.settings.trash_min_age_days != min_age_days {
+                        self.refresh_trash_summary();
+                    }
+                    self.current_screen = Screen::Selection;
+                }
+            }
+            Screen::Progress => {
+                if let Some(progress) = &mut self.progress {
+                    if let FinderEvent::Exit = progress.handle_key(key) {
+                        self.finish_execution();
                     }
                 }
             }
         }
+        Effect::None
+    }
+
+    pub fn run(&mut self) -> Result<(), io::Error> {
+        // Restores the terminal however this returns
+        let _guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+        loop {
+            terminal.draw(|f| self.draw(f))?;
+
+            // While a scan runs in the background, wake up regularly to check whether it is done
+            let scanning = self.file_finder.is_scanning() || self.duplicate_finder.is_scanning() || self.dashboard.is_measuring();
+            if scanning && !event::poll(Duration::from_millis(100))? {
+                self.file_finder.poll_scan();
+                self.duplicate_finder.poll_scan();
+                self.dashboard.poll();
+                continue;
+            }
+
+            // Pick up the output of running operations as it comes in
+            if let Some(progress) = &mut self.progress {
+                if progress.is_running() && !event::poll(Duration::from_millis(50))? {
+                    progress.poll();
+                    continue;
+                }
+            }
+
+            // Waiting for another package manager to release the pacman database, then carry on by ourselves
+            if self.waiting_for_lock && !event::poll(Duration::from_millis(500))? {
+                self.pacman_lock = pacman_lock::check();
+                if matches!(self.pacman_lock, LockState::Free) {
+                    self.waiting_for_lock = false;
+                    self.start_execution(&mut terminal)?;
+                }
+                continue;
+            }
+
+            let key = match event::read()? {
+                Event::Key(key) => key.code,
+                Event::Mouse(mouse) => match self.handle_mouse(mouse) {
+                    Some(key) => key,
+                    None => continue,
+                },
+                // Redraw at the new size rather than waiting for a key
+                Event::Resize(..) => {
+                    terminal.autoresize()?;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            match self.handle_key(key) {
+                Effect::None => {}
+                Effect::Quit => break,
+                Effect::InstallMissing => self.install_missing(&mut terminal)?,
+                Effect::StartExecution => self.start_execution(&mut terminal)?,
+                // Removing the lock may ask for a password, which needs the terminal back
+                Effect::RemoveStaleLock => {
+                    self.lock_error = terminal::suspend(&mut terminal, pacman_lock::remove_stale_lock)?.err();
+                    self.pacman_lock = pacman_lock::check();
+                }
+                // Installing system timers may ask for a password, which needs the terminal back
+                Effect::ApplySchedule => terminal::suspend(&mut terminal, || self.schedule_view.apply())?,
+            }
+        }
 
         Ok(())
     }
 }
 
-fn main() -> Result<(), io::Error> {
-    let mut tui = ArchTui::new();
-    tui.run()
+// Joins words with spaces into lines of at most width characters, or one word where a word is longer
+fn wrap_words(words: &[String], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.clone()),
+        }
+    }
+    lines
 }
+
+#[cfg(test)]
+mod tests;
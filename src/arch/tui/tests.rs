@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::PathBuf;
+use crossterm::event::KeyCode;
+use tui::{backend::TestBackend, Terminal};
+use super::{ArchTui, Effect, Screen};
+use crate::arch::prerequisites::Unmet;
+use crate::config::Config;
+use crate::execution::OperationError;
+use crate::trash::TrashSummary;
+
+const WIDTH: u16 = 100;
+const HEIGHT: u16 = 30;
+
+// A menu on the selection screen with every operation usable and the default settings, whatever this machine
+// has installed or configured
+fn menu() -> ArchTui {
+    let mut tui = ArchTui::unprobed(TrashSummary::default());
+    tui.current_screen = Screen::Selection;
+    tui.settings = Config::default();
+    tui
+}
+
+fn press(tui: &mut ArchTui, keys: &[KeyCode]) -> Effect {
+    keys.iter().fold(Effect::None, |_, key| tui.handle_key(*key))
+}
+
+// Keys that highlight an operation by jumping to its category and moving down from there
+fn go_to(category: char, down: usize) -> Vec<KeyCode> {
+    std::iter::once(KeyCode::Char(category)).chain(std::iter::repeat_n(KeyCode::Char('j'), down)).collect()
+}
+
+fn render(tui: &ArchTui) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+    terminal.draw(|f| tui.draw(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..buffer.area.height {
+        let line: String = (0..buffer.area.width).map(|x| buffer.get(x, y).symbol.as_str()).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+// Compares the screen with snapshots/<name>.txt. Run with UPDATE_SNAPSHOTS=1 to write the files after an
+// intended change, then review the diff.
+fn assert_snapshot(name: &str, tui: &ArchTui) {
+    let actual = render(tui);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/arch/tui/snapshots").join(format!("{}.txt", name));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {}, run with UPDATE_SNAPSHOTS=1 to create it", path.display(), e));
+    assert!(expected == actual, "{} changed, the screen is now:\n{}", path.display(), actual);
+}
+
+fn unmet(item: &str) -> (String, Unmet) {
+    let unmet = Unmet { error: OperationError::CommandMissing(String::from("paccache (pacman-contrib)")), package: Some("pacman-contrib") };
+    (item.to_string(), unmet)
+}
+
+fn set_unmet(tui: &mut ArchTui, (label, unmet): (String, Unmet)) {
+    let item = tui.items.iter_mut().find(|item| item.label == label).unwrap();
+    item.unmet = Some(unmet);
+}
+
+#[test]
+fn selection_screen() {
+    assert_snapshot("selection", &menu());
+}
+
+#[test]
+fn toggling_a_category_checks_its_usable_operations() {
+    let mut tui = menu();
+    press(&mut tui, &[KeyCode::Char('3'), KeyCode::Enter]);
+    assert_eq!(
+        tui.selected_operation_ids(),
+        ["clear-systemd-journal", "clean-user-cache", "clean-browser-caches", "clean-stale-thumbnails", "empty-trash"]
+    );
+    assert_snapshot("category_checked", &tui);
+
+    press(&mut tui, &[KeyCode::Enter]);
+    assert!(tui.selected_operation_ids().is_empty());
+}
+
+#[test]
+fn toggling_a_category_skips_unavailable_operations() {
+    let mut tui = menu();
+    set_unmet(&mut tui, unmet("Clean package cache"));
+    press(&mut tui, &[KeyCode::Char('1'), KeyCode::Enter]);
+    assert_eq!(tui.selected_operation_ids(), ["remove-orphaned-packages"]);
+}
+
+#[test]
+fn toggling_a_category_while_searching_checks_only_the_matches() {
+    let mut tui = menu();
+    press(&mut tui, &[KeyCode::Char('/'), KeyCode::Char('b'), KeyCode::Char('r'), KeyCode::Char('o'), KeyCode::Char('w'), KeyCode::Enter]);
+    press(&mut tui, &[KeyCode::Char('1'), KeyCode::Enter]);
+    assert_eq!(tui.selected_operation_ids(), ["clean-browser-caches"]);
+    assert_snapshot("search", &tui);
+}
+
+#[test]
+fn checking_all_matches_keeps_hidden_checks() {
+    let mut tui = menu();
+    press(&mut tui, &go_to('3', 6));
+    press(&mut tui, &[KeyCode::Char(' ')]);
+    press(&mut tui, &[KeyCode::Char('/'), KeyCode::Char('b'), KeyCode::Char('r'), KeyCode::Char('o'), KeyCode::Char('w'), KeyCode::Enter]);
+    press(&mut tui, &[KeyCode::Char('a')]);
+    assert_eq!(tui.selected_operation_ids(), ["clean-browser-caches", "empty-trash"]);
+}
+
+#[test]
+fn confirmation_needs_a_checked_operation() {
+    let mut tui = menu();
+    press(&mut tui, &[KeyCode::Char('c')]);
+    assert!(matches!(tui.current_screen, Screen::Selection));
+
+    press(&mut tui, &go_to('3', 3));
+    press(&mut tui, &[KeyCode::Char(' '), KeyCode::Char('c')]);
+    assert!(matches!(tui.current_screen, Screen::Confirmation));
+    assert_snapshot("confirmation", &tui);
+
+    assert_eq!(press(&mut tui, &[KeyCode::Enter]), Effect::StartExecution);
+    press(&mut tui, &[KeyCode::Esc]);
+    assert!(matches!(tui.current_screen, Screen::Selection));
+}
+
+#[test]
+fn confirmation_waits_for_a_locked_database() {
+    let mut tui = menu();
+    press(&mut tui, &go_to('3', 3));
+    press(&mut tui, &[KeyCode::Char(' '), KeyCode::Char('c')]);
+    tui.pacman_lock = crate::arch::pacman_lock::LockState::Stale;
+    assert_eq!(press(&mut tui, &[KeyCode::Enter]), Effect::None);
+    assert_eq!(press(&mut tui, &[KeyCode::Char('r')]), Effect::RemoveStaleLock);
+}
+
+#[test]
+fn description_of_the_highlighted_operation() {
+    let mut tui = menu();
+    press(&mut tui, &go_to('3', 6));
+    assert_snapshot("description", &tui);
+}
+
+#[test]
+fn description_of_an_unavailable_operation() {
+    let mut tui = menu();
+    set_unmet(&mut tui, unmet("Clean package cache"));
+    press(&mut tui, &go_to('1', 1));
+    assert_snapshot("description_unavailable", &tui);
+
+    // It can't be checked, only the missing package installed
+    press(&mut tui, &[KeyCode::Char(' ')]);
+    assert!(tui.selected_operation_ids().is_empty());
+    assert_eq!(press(&mut tui, &[KeyCode::Char('i')]), Effect::InstallMissing);
+}
+
+#[test]
+fn help_closes_on_any_key() {
+    let mut tui = menu();
+    press(&mut tui, &[KeyCode::Char('?')]);
+    assert_snapshot("help", &tui);
+    assert_eq!(press(&mut tui, &[KeyCode::Char('q')]), Effect::None);
+    assert_eq!(press(&mut tui, &[KeyCode::Char('q')]), Effect::Quit);
+}
@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::filesystem::{dir_size, home_dir, remove_path};
+
+enum LockKind {
+    // Firefox: `lock` symlink pointing at "<ip>:+<pid>"
+    Firefox,
+    // Chromium family: `SingletonLock` symlink pointing at "<hostname>-<pid>"
+    Chromium,
+}
+
+pub struct BrowserProfile {
+    pub browser: String,
+    pub name: String,
+    profile_dir: PathBuf,
+    lock_kind: LockKind,
+    // Flatpak installs run in their own PID namespace
+    sandboxed: bool,
+    pub cache_dirs: Vec<PathBuf>,
+}
+
+#[derive(Default)]
+pub struct BrowserCleanReport {
+    pub cleaned_profiles: Vec<String>,
+    pub running_profiles: Vec<String>,
+    pub bytes_freed: u64,
+    pub errors: Vec<io::Error>,
+}
+
+// Chromium based browsers: (name, config dir relative to the config root, flatpak app ID)
+const CHROMIUM_BROWSERS: [(&str, &str, &str); 4] = [
+    ("Chromium", "chromium", "org.chromium.Chromium"),
+    ("Google Chrome", "google-chrome", "com.google.Chrome"),
+    ("Brave", "BraveSoftware/Brave-Browser", "com.brave.Browser"),
+    ("Vivaldi", "vivaldi", "com.vivaldi.Vivaldi"),
+];
+
+// Cache directories inside a Chromium profile, in both the config and the cache root
+const CHROMIUM_CACHE_DIRS: [&str; 5] = ["Cache", "Code Cache", "GPUCache", "DawnCache", "DawnGraphiteCache"];
+
+impl BrowserProfile {
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.browser, self.name)
+    }
+
+    // Whether the lock file shows the browser running on this machine. A lock whose process is gone is stale.
+    // The PID in a flatpak's lock is only meaningful inside its sandbox, so there any lock counts as held.
+    pub fn is_running(&self) -> bool {
+        let (lock, separator) = match self.lock_kind {
+            LockKind::Firefox => (self.profile_dir.join("lock"), '+'),
+            LockKind::Chromium => (self.profile_root().join("SingletonLock"), '-'),
+        };
+        if self.sandboxed {
+            return fs::symlink_metadata(&lock).is_ok();
+        }
+        let target = match fs::read_link(&lock) {
+            Ok(target) => target.to_string_lossy().into_owned(),
+            // Not a symlink: treat an existing lock as held rather than guess
+            Err(_) => return lock.exists(),
+        };
+        match target.rsplit(separator).next().and_then(|pid| pid.parse::<u32>().ok()) {
+            Some(pid) => Path::new("/proc").join(pid.to_string()).exists(),
+            None => true,
+        }
+    }
+
+    // Chromium keeps its lock in the user data dir, one level above the profile
+    fn profile_root(&self) -> &Path {
+        self.profile_dir.parent().unwrap_or(&self.profile_dir)
+    }
+}
+
+pub fn discover_profiles() -> Vec<BrowserProfile> {
+    let home = match home_dir() {
+        Some(home) => home,
+        None => return Vec::new(),
+    };
+    let mut profiles = Vec::new();
+
+    // Native installs use ~/.mozilla, ~/.config and ~/.cache, flatpaks keep the same layout under ~/.var/app/<id>
+    let flatpak = |id: &str| home.join(".var/app").join(id);
+    firefox_profiles(&home.join(".mozilla/firefox"), &home.join(".cache/mozilla/firefox"), "Firefox", false, &mut profiles);
+    let firefox_flatpak = flatpak("org.mozilla.firefox");
+    firefox_profiles(
+        &firefox_flatpak.join(".mozilla/firefox"),
+        &firefox_flatpak.join("cache/mozilla/firefox"),
+        "Firefox (flatpak)",
+        true,
+        &mut profiles,
+    );
+
+    for (browser, dir, app_id) in CHROMIUM_BROWSERS {
+        chromium_profiles(&home.join(".config").join(dir), &home.join(".cache").join(dir), browser, false, &mut profiles);
+        let app = flatpak(app_id);
+        if app.is_dir() {
+            let browser = format!("{} (flatpak)", browser);
+            chromium_profiles(&app.join("config").join(dir), &app.join("cache").join(dir), &browser, true, &mut profiles);
+        }
+    }
+
+    profiles
+}
+
+fn firefox_profiles(profiles_root: &Path, cache_root: &Path, browser: &str, sandboxed: bool, profiles: &mut Vec<BrowserProfile>) {
+    let entries = match fs::read_dir(profiles_root) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let profile_dir = entry.path();
+        // Every profile has a prefs.js, which tells it apart from "Crash Reports" and friends
+        if !profile_dir.join("prefs.js").exists() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let cache_dirs = ["cache2", "startupCache", "thumbnails"]
+            .iter()
+            .map(|dir| cache_root.join(&name).join(dir))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        profiles.push(BrowserProfile { browser: browser.to_string(), name, profile_dir, lock_kind: LockKind::Firefox, sandboxed, cache_dirs });
+    }
+}
+
+fn chromium_profiles(config_root: &Path, cache_root: &Path, browser: &str, sandboxed: bool, profiles: &mut Vec<BrowserProfile>) {
+    let entries = match fs::read_dir(config_root) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let profile_dir = entry.path();
+        // Profiles are "Default", "Profile 1", "Guest Profile" etc, all of which have a Preferences file
+        if !profile_dir.join("Preferences").exists() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let mut cache_dirs: Vec<PathBuf> = CHROMIUM_CACHE_DIRS
+            .iter()
+            .flat_map(|dir| [profile_dir.join(dir), cache_root.join(&name).join(dir)])
+            .filter(|dir| dir.is_dir())
+            .collect();
+        // Shader caches are shared by all profiles of a browser, only attach them to the first one
+        if !profiles.iter().any(|p| p.browser == browser) {
+            cache_dirs.extend(["ShaderCache", "GrShaderCache", "GraphiteDawnCache"].iter().map(|dir| config_root.join(dir)).filter(|dir| dir.is_dir()));
+        }
+        profiles.push(BrowserProfile { browser: browser.to_string(), name, profile_dir, lock_kind: LockKind::Chromium, sandboxed, cache_dirs });
+    }
+}
+
+// Size of the caches clean_caches would remove right now
+pub fn cache_size() -> u64 {
+    discover_profiles()
+        .iter()
+        .filter(|profile| !profile.is_running())
+        .flat_map(|profile| profile.cache_dirs.iter())
+        .map(|dir| dir_size(dir))
+        .sum()
+}
+
+// Empties the cache directories of every profile whose browser is not running, except those keep picks
+pub fn clean_caches(keep: impl Fn(&Path) -> bool) -> BrowserCleanReport {
+    let mut report = BrowserCleanReport::default();
+    for profile in discover_profiles() {
+        if profile.is_running() {
+            report.running_profiles.push(profile.label());
+            continue;
+        }
+
+        let mut failed = false;
+        for dir in profile.cache_dirs.iter().filter(|dir| !keep(dir)) {
+            let size = dir_size(dir);
+            match remove_path(dir) {
+                Ok(()) => report.bytes_freed += size,
+                Err(e) => {
+                    report.errors.push(e);
+                    failed = true;
+                }
+            }
+        }
+        if !failed {
+            report.cleaned_profiles.push(profile.label());
+        }
+    }
+    report
+}
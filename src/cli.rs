@@ -0,0 +1,453 @@
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use colored::Colorize;
+use serde::Serialize;
+use crate::arch::executor;
+use crate::arch::operations::ArchOperation;
+use crate::arch::pacman_lock::{self, LockState};
+use crate::arch::registry;
+use crate::config;
+use crate::execution::{ExecutionEvent, OperationError};
+use crate::filesystem::format_size;
+use crate::history;
+use crate::privilege;
+use crate::quarantine;
+use crate::report::{self, Event, Host, OperationReport, PlannedOperation, RunReport, Totals};
+use crate::schedule::{self, Kind, Schedule, Scope};
+use crate::timestamp;
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+}
+
+#[derive(Clone, Copy)]
+struct RunOptions {
+    format: OutputFormat,
+    wait_for_lock: bool, // Wait for a locked pacman database instead of asking, or skipping without a terminal
+}
+
+// `debloater undo [RUN [PATH...]]`: lists quarantine runs, or restores a whole run or some of its files
+pub fn undo(args: &[String]) -> Result<(), String> {
+    let mut runs = quarantine::list_runs();
+    let run_name = match args.first() {
+        Some(name) => name,
+        None => {
+            if runs.is_empty() {
+                println!("The quarantine is empty");
+                return Ok(());
+            }
+            println!("{}", "Quarantined runs:".bold());
+            for run in &runs {
+                println!(
+                    "  {}  {}, {} ({} items, {})",
+                    run.name.bold(),
+                    run.manifest.operation,
+                    timestamp::format_age(run.manifest.created),
+                    run.pending_entries().count(),
+                    format_size(run.size())
+                );
+                for entry in run.pending_entries() {
+                    println!("      {}", entry.original_path.display());
+                }
+            }
+            println!("\nRestore a run with `debloater undo <run>`, or single files with `debloater undo <run> <path>...`");
+            return Ok(());
+        }
+    };
+
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let paths: Vec<PathBuf> = args[1..].iter().map(|path| current_dir.join(path)).collect();
+    let paths = if paths.is_empty() { None } else { Some(paths.as_slice()) };
+
+    let mut matched = false;
+    let mut restored = 0;
+    let mut errors = Vec::new();
+    for run in runs.iter_mut().filter(|run| &run.name == run_name) {
+        matched = true;
+        match quarantine::restore(run, paths) {
+            Ok(count) => restored += count,
+            Err(e) => errors.push(e),
+        }
+    }
+    if !matched {
+        return Err(format!("No quarantine run named {}, run `debloater undo` to list them", run_name));
+    }
+
+    println!("Restored {} items from {}", restored, run_name.bold());
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+// `debloater history`: lists past runs, with a running total of the space reclaimed
+pub fn history() -> Result<(), String> {
+    let runs = history::load();
+    if runs.is_empty() {
+        println!("No runs recorded yet");
+        return Ok(());
+    }
+
+    println!("{}", "Run history:".bold());
+    let mut total = 0;
+    for run in &runs {
+        total += run.bytes_freed;
+        println!(
+            "  {}  {} ({})  {} succeeded, {} failed, {} skipped  freed {}  total {}",
+            timestamp::format(run.timestamp).bold(),
+            run.hostname,
+            run.distro,
+            run.count("succeeded"),
+            run.count("failed"),
+            run.count("skipped"),
+            format_size(run.bytes_freed),
+            format_size(total)
+        );
+        for operation in &run.operations {
+            match &operation.error {
+                Some(error) => println!("      {}: {} ({})", operation.name, operation.outcome, error),
+                None => println!("      {}: {}, {}", operation.name, operation.outcome, format_size(operation.bytes_freed)),
+            }
+        }
+        if let Some(snapshot) = &run.snapshot {
+            println!("      Snapshot: {}", snapshot.describe());
+        }
+    }
+    println!(
+        "\nReclaimed {} over {} runs since {}",
+        format_size(total).bold(),
+        runs.len(),
+        timestamp::format(runs[0].timestamp)
+    );
+    Ok(())
+}
+
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+
+// Splits `[--output text|json|ndjson] [--wait-for-lock] ARG...` into the options and the other arguments
+fn parse_run_args(args: &[String]) -> Result<(RunOptions, Vec<String>), String> {
+    let mut options = RunOptions { format: OutputFormat::Text, wait_for_lock: false };
+    let mut ids = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--wait-for-lock" {
+            options.wait_for_lock = true;
+            continue;
+        }
+        let value = match arg.strip_prefix("--output=") {
+            Some(value) => Some(value.to_string()),
+            None if arg == "--output" || arg == "-o" => Some(args.next().cloned().ok_or("--output needs a format")?),
+            None => None,
+        };
+        match value.as_deref() {
+            Some("text") => options.format = OutputFormat::Text,
+            Some("json") => options.format = OutputFormat::Json,
+            Some("ndjson") => options.format = OutputFormat::Ndjson,
+            Some(other) => return Err(format!("Unknown output format {}, expected text, json or ndjson", other)),
+            None if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            None => ids.push(arg.clone()),
+        }
+    }
+    Ok((options, ids))
+}
+
+// `debloater run [--output text|json|ndjson] [--wait-for-lock] OPERATION...`: runs operations by id without the
+// TUI, eg. from a timer or a fleet management tool
+pub fn run(args: &[String]) -> Result<(), String> {
+    let (options, ids) = parse_run_args(args)?;
+    if ids.is_empty() {
+        println!("{}", "Operations that can be run with `debloater run`:".bold());
+        for entry in registry::runnable() {
+            println!("  {:<26} {}", entry.id, entry.name);
+        }
+        return Ok(());
+    }
+    run_operations(&ids, options)
+}
+
+// `debloater profile [list]` and `debloater profile run NAME [--output text|json|ndjson] [--wait-for-lock]`
+pub fn profile(args: &[String]) -> Result<(), String> {
+    let profiles = &config::get().profiles;
+    match args.first().map(String::as_str) {
+        None | Some("list") => {
+            if profiles.is_empty() {
+                println!("No profiles defined, add them to {} or save one from the TUI", config_path_hint());
+                return Ok(());
+            }
+            println!("{}", "Profiles:".bold());
+            for (name, profile) in profiles {
+                println!("  {:<16} {}", name.bold(), profile.operations.join(", "));
+                if let Some(description) = &profile.description {
+                    println!("  {:<16} {}", "", description);
+                }
+            }
+            Ok(())
+        }
+        Some("run") => {
+            let name = args.get(1).ok_or("Usage: debloater profile run NAME [--output text|json|ndjson] [--wait-for-lock]")?;
+            let profile = profiles
+                .get(name)
+                .ok_or_else(|| format!("No profile named {}, run `debloater profile list` to list them", name))?;
+            let (options, extra) = parse_run_args(&args[2..])?;
+            if let Some(arg) = extra.first() {
+                return Err(format!("Unexpected argument {}", arg));
+            }
+            if profile.operations.is_empty() {
+                return Err(format!("Profile {} has no operations", name));
+            }
+            run_operations(&profile.operations, options)
+        }
+        Some(other) => Err(format!("Unknown profile command {}, expected list or run", other)),
+    }
+}
+
+struct ScheduleArgs {
+    scope: Option<Scope>,
+    frequency: Option<String>,
+    allow_package_removal: bool,
+    rest: Vec<String>,
+}
+
+// Splits `[--system|--user] [--frequency CALENDAR] [--allow-package-removal]` from the other arguments
+fn parse_schedule_args(args: &[String]) -> Result<ScheduleArgs, String> {
+    let mut scope = None;
+    let mut frequency = None;
+    let mut allow_package_removal = false;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--system" => scope = Some(Scope::System),
+            "--user" => scope = Some(Scope::User),
+            "--allow-package-removal" => allow_package_removal = true,
+            "--frequency" | "-f" => frequency = Some(args.next().cloned().ok_or("--frequency needs a value")?),
+            _ if arg.starts_with("--frequency=") => frequency = arg.strip_prefix("--frequency=").map(String::from),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ => rest.push(arg.clone()),
+        }
+    }
+    Ok(ScheduleArgs { scope, frequency, allow_package_removal, rest })
+}
+
+// `debloater install-schedule PROFILE [--frequency CALENDAR] [--system|--user] [--allow-package-removal]`: runs a
+// profile from a systemd timer. Profiles with operations that need root get a system timer unless told otherwise.
+pub fn install_schedule(args: &[String]) -> Result<(), String> {
+    let usage = "Usage: debloater install-schedule PROFILE [--frequency daily|weekly|monthly|CALENDAR] [--system|--user] [--allow-package-removal]";
+    let ScheduleArgs { scope, frequency, allow_package_removal, rest } = parse_schedule_args(args)?;
+    let name = match rest.as_slice() {
+        [name] => name,
+        _ => return Err(String::from(usage)),
+    };
+    let profile = config::get()
+        .profiles
+        .get(name)
+        .ok_or_else(|| format!("No profile named {}, run `debloater profile list` to list them", name))?;
+    let operations: Vec<ArchOperation> = profile.operations.iter().filter_map(|id| ArchOperation::from_id(id)).collect();
+    // Nobody sees what these remove when they run from a timer, so they have to be asked for
+    if let Some(operation) = operations.iter().find(|operation| operation.needs_opt_in()).filter(|_| !allow_package_removal) {
+        return Err(format!(
+            "{} is part of {}, and removes packages without anyone seeing which. Add --allow-package-removal to schedule it anyway",
+            operation.id(),
+            name
+        ));
+    }
+    let needs_root = operations.iter().any(ArchOperation::needs_root);
+    let scope = scope.unwrap_or(if needs_root { Scope::System } else { Scope::User });
+    // A system timer runs as root, which only reads the system config
+    if scope == Scope::System && !profile.system {
+        return Err(format!(
+            "{} is not in {}, so a system timer running as root would not find it. Move it there, or add --user to run it as you",
+            name,
+            config::SYSTEM_CONFIG
+        ));
+    }
+    if scope == Scope::User && needs_root {
+        eprintln!("Warning: {} has operations that need root, which will be skipped unless the credentials are cached", name);
+    }
+
+    let schedule = Schedule {
+        kind: Kind::Profile,
+        name: name.clone(),
+        scope,
+        frequency: frequency.unwrap_or_else(|| String::from("weekly")),
+        args: vec![String::from("profile"), String::from("run"), name.clone()],
+    };
+    schedule::install(&schedule)?;
+    println!("Installed a {} timer running profile {} {}", scope.name(), name.bold(), schedule.frequency);
+    println!("Check on it with `debloater status`, remove it with `debloater uninstall-schedule {}`", name);
+    Ok(())
+}
+
+// `debloater uninstall-schedule NAME [--system|--user]`: without a scope, removes the profile's schedule from both.
+// Category schedules are changed on the schedule screen of the TUI.
+pub fn uninstall_schedule(args: &[String]) -> Result<(), String> {
+    let ScheduleArgs { scope, frequency, allow_package_removal, rest } = parse_schedule_args(args)?;
+    let name = match (rest.as_slice(), frequency, allow_package_removal) {
+        ([name], None, false) => name,
+        _ => return Err(String::from("Usage: debloater uninstall-schedule NAME [--system|--user]")),
+    };
+    let scopes = match scope {
+        Some(scope) => vec![scope],
+        None => vec![Scope::System, Scope::User],
+    };
+    let mut removed = false;
+    for scope in scopes {
+        if schedule::uninstall(Kind::Profile, name, scope)? {
+            println!("Removed the {} timer {}", scope.name(), name.bold());
+            removed = true;
+        }
+    }
+    if removed {
+        Ok(())
+    } else {
+        Err(format!("No schedule for a profile named {}, run `debloater status` to list them", name))
+    }
+}
+
+// `debloater status`: the installed schedules and how their last runs went
+pub fn status() -> Result<(), String> {
+    let schedules = schedule::installed();
+    if schedules.is_empty() {
+        println!("No schedules installed, add one with `debloater install-schedule PROFILE` or from the TUI");
+        return Ok(());
+    }
+    println!("{}", "Schedules:".bold());
+    for installed in &schedules {
+        let schedule = &installed.schedule;
+        println!("  {} ({} {}, {})", schedule.name.bold(), schedule.scope.name(), schedule.kind.name(), schedule.frequency);
+        println!("      runs:      debloater {}", schedule.args.join(" "));
+        println!("      next run:  {}", installed.next_run.as_deref().unwrap_or("not scheduled, is the timer enabled?"));
+        match (&installed.last_run, installed.last_result.as_deref()) {
+            (Some(last), Some("success")) => println!("      last run:  {}, {}", last, "succeeded".green()),
+            (Some(last), Some(result)) => println!("      last run:  {}, {}", last, format!("failed ({})", result).red()),
+            (Some(last), None) => println!("      last run:  {}", last),
+            (None, _) => println!("      last run:  never"),
+        }
+    }
+    Ok(())
+}
+
+fn config_path_hint() -> String {
+    config::user_config_path().map(|path| path.display().to_string()).unwrap_or_else(|| String::from(config::SYSTEM_CONFIG))
+}
+
+fn run_operations(ids: &[String], options: RunOptions) -> Result<(), String> {
+    let format = options.format;
+    let operations = ids
+        .iter()
+        .map(|id| ArchOperation::from_id(id).ok_or_else(|| format!("Unknown operation {}, run `debloater run` to list them", id)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let planned: Vec<PlannedOperation> = operations
+        .iter()
+        .enumerate()
+        .map(|(index, operation)| PlannedOperation {
+            index,
+            id: operation.id(),
+            name: operation.name(),
+            estimated_bytes: operation.estimate_bytes(),
+        })
+        .collect();
+    let host = Host::current();
+    let started = timestamp::now();
+    if format == OutputFormat::Ndjson {
+        print_json(&Event::RunStarted { schema_version: report::SCHEMA_VERSION, timestamp: started, host: &host, operations: &planned });
+    }
+
+    // Ask about a locked database and for a password only when someone is there to answer. Otherwise package
+    // operations are skipped if the database is locked, and root relies on cached credentials.
+    let interactive = unsafe { libc::isatty(libc::STDIN_FILENO) } == 1;
+    let lock = if operations.iter().any(ArchOperation::uses_pacman_db) { pacman_lock::check() } else { LockState::Free };
+    if !matches!(lock, LockState::Free) {
+        if options.wait_for_lock {
+            eprintln!("{}, waiting for it to be released...", lock.describe());
+            pacman_lock::wait_until_free();
+        } else if interactive {
+            if let Err(e) = pacman_lock::resolve_interactively() {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    let mut root_error = None;
+    let keep_alive = if operations.iter().any(ArchOperation::needs_root) {
+        let result = if interactive { privilege::begin_batch(config::get().escalation) } else { privilege::init_batch(config::get().escalation).map(|_| None) };
+        result.unwrap_or_else(|e| {
+            root_error = Some(OperationError::PermissionDenied(e));
+            None
+        })
+    } else {
+        None
+    };
+
+    let receiver = executor::spawn(operations, root_error, keep_alive, Arc::new(AtomicBool::new(false)));
+    let mut reports = Vec::new();
+    let mut snapshot = None;
+    let mut current = None;
+    for event in receiver {
+        match event {
+            ExecutionEvent::Started(index) => {
+                current = Some(index);
+                if format == OutputFormat::Ndjson {
+                    print_json(&Event::OperationStarted { index, id: planned[index].id });
+                }
+            }
+            ExecutionEvent::Output(line) => match format {
+                OutputFormat::Text => println!("{}", line),
+                OutputFormat::Ndjson => print_json(&Event::Output { index: current, line: &line }),
+                OutputFormat::Json => {}
+            },
+            ExecutionEvent::Finished(index, result) => {
+                current = None;
+                let report = OperationReport::new(&planned[index], &result);
+                if format == OutputFormat::Ndjson {
+                    print_json(&Event::OperationFinished(&report));
+                }
+                reports.push(report);
+            }
+            ExecutionEvent::Snapshot(record) => {
+                if format == OutputFormat::Ndjson {
+                    print_json(&Event::Snapshot(&record));
+                }
+                snapshot = Some(record);
+            }
+            ExecutionEvent::Done => break,
+        }
+    }
+
+    let totals = Totals::new(&reports);
+    match format {
+        OutputFormat::Text => println!(
+            "\n{} succeeded, {} failed, {} skipped. Freed {}.",
+            totals.succeeded,
+            totals.failed,
+            totals.skipped,
+            format_size(totals.bytes_freed).bold()
+        ),
+        OutputFormat::Json => print_json(&RunReport {
+            schema_version: report::SCHEMA_VERSION,
+            timestamp: started,
+            host: &host,
+            operations: &reports,
+            totals: &totals,
+            snapshot: snapshot.as_ref(),
+        }),
+        OutputFormat::Ndjson => print_json(&Event::RunFinished(&totals)),
+    }
+
+    if totals.failed > 0 {
+        Err(format!("{} of {} operations failed", totals.failed, reports.len()))
+    } else {
+        Ok(())
+    }
+}
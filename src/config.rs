@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml::{Table, Value};
+use crate::arch::registry;
+use crate::filesystem::{home_dir, xdg_dir};
+use crate::privilege::Escalation;
+use crate::quarantine;
+use crate::theme::ThemeName;
+use crate::trash;
+
+pub const SYSTEM_CONFIG: &str = "/etc/debloater/config.toml";
+
+// Upper bound of every setting counted in days, far beyond any useful age and far from overflowing as seconds
+pub const MAX_DAYS: u64 = 36500;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+// Reports a problem with the key at the given path
+type ErrorFn<'a> = dyn FnMut(&str, String) + 'a;
+
+#[derive(Clone, Default)]
+pub struct Profile {
+    pub description: Option<String>,
+    pub operations: Vec<String>, // Operation ids
+    pub system: bool,            // Defined in the system config and not replaced by the user's, so root runs it too
+}
+
+#[derive(Clone)]
+pub struct Config {
+    pub escalation: Option<Escalation>,
+    pub theme: ThemeName,
+    pub excluded_paths: Vec<PathBuf>,
+    pub default_selection: Vec<String>, // Operation ids checked when the TUI opens
+    pub package_cache_keep: u32,
+    pub journal_vacuum_time: String,
+    pub user_cache_min_age_days: u64,
+    pub user_cache_exclude: Vec<String>, // Names in the user cache to leave alone, with * wildcards
+    pub trash_min_age_days: u64,
+    pub quarantine_retention_days: u64,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            escalation: None,
+            theme: ThemeName::Dark,
+            excluded_paths: Vec::new(),
+            default_selection: Vec::new(),
+            package_cache_keep: 3,
+            journal_vacuum_time: String::from("1d"),
+            user_cache_min_age_days: 0,
+            user_cache_exclude: Vec::new(),
+            trash_min_age_days: trash::DEFAULT_MIN_AGE_DAYS,
+            quarantine_retention_days: quarantine::DEFAULT_RETENTION_DAYS,
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    // Whether removing a path would remove an excluded one: the path is one, is inside one, or contains one
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.excluded_paths.iter().any(|excluded| path.starts_with(excluded) || excluded.starts_with(path))
+    }
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config").map(|config| config.join("debloater").join("config.toml"))
+}
+
+// Loads the system config and then the user config on top of it. Only the first call has any effect.
+// Every problem found in either file is returned, each naming the file and the key it is about.
+pub fn init() -> Result<&'static Config, Vec<String>> {
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+    let mut config = Config::default();
+    let mut errors = Vec::new();
+    for path in [Some(PathBuf::from(SYSTEM_CONFIG)), user_config_path()].into_iter().flatten() {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        match contents.parse::<Table>() {
+            Ok(table) => {
+                apply(&table, &mut config, &mut |key, message| errors.push(format!("{}: {}: {}", path.display(), key, message)));
+                // The system config comes first, so every profile so far is one of its own
+                if path == Path::new(SYSTEM_CONFIG) {
+                    config.profiles.values_mut().for_each(|profile| profile.system = true);
+                }
+            }
+            Err(e) => errors.push(format!("{}: {}", path.display(), e.to_string().trim_end())),
+        }
+    }
+    if errors.is_empty() {
+        Ok(CONFIG.get_or_init(|| config))
+    } else {
+        Err(errors)
+    }
+}
+
+// The loaded config, or the defaults if none was loaded
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+fn apply(table: &Table, config: &mut Config, error: &mut ErrorFn<'_>) {
+    for (key, value) in table {
+        match key.as_str() {
+            "escalation" => match value.as_str().map(|name| (name, Escalation::from_name(name))) {
+                Some((_, Some(escalation))) => config.escalation = Some(escalation),
+                Some((name, None)) => error(key, format!("unknown tool \"{}\", expected sudo, doas, run0 or pkexec", name)),
+                None => error(key, String::from("expected a string")),
+            },
+            "theme" => match value.as_str().map(|name| (name, ThemeName::from_name(name))) {
+                Some((_, Some(theme))) => config.theme = theme,
+                Some((name, None)) => error(key, format!("unknown theme \"{}\", expected dark, light, high-contrast or monochrome", name)),
+                None => error(key, String::from("expected a string")),
+            },
+            "excluded_paths" => {
+                if let Some(paths) = string_list(key, value, error) {
+                    let paths: Vec<PathBuf> = paths.iter().map(|path| expand_home(path)).collect();
+                    // Paths to remove are always absolute, a relative one would never match any of them
+                    match paths.iter().find(|path| !path.is_absolute()) {
+                        Some(path) => error(key, format!("\"{}\" is not an absolute path, start it with / or ~/", path.display())),
+                        None => config.excluded_paths = paths,
+                    }
+                }
+            }
+            "default_selection" => {
+                if let Some(ids) = operation_list(key, value, error) {
+                    config.default_selection = ids;
+                }
+            }
+            // A profile in the user config replaces one of the same name in the system config as a whole
+            "profiles" => {
+                for_each_in_table(key, value, error, &mut |key, name, value, error| {
+                    let mut profile = Profile::default();
+                    for_each_in_table(key, value, error, &mut |key, field, value, error| match field {
+                        "operations" => profile.operations = operation_list(key, value, error).unwrap_or_default(),
+                        "description" => match value.as_str() {
+                            Some(description) => profile.description = Some(description.to_string()),
+                            None => error(key, String::from("expected a string")),
+                        },
+                        _ => error(key, String::from("unknown key")),
+                    });
+                    if !is_profile_name(name) {
+                        error(key, String::from("profile names can only contain letters, digits, - and _"));
+                    }
+                    config.profiles.insert(name.to_string(), profile);
+                });
+            }
+            "quarantine" => {
+                for_each_in_table(key, value, error, &mut |key, name, value, error| match name {
+                    "retention_days" => set_number(key, value, 1, MAX_DAYS, &mut config.quarantine_retention_days, error),
+                    _ => error(key, String::from("unknown key")),
+                });
+            }
+            "operations" => {
+                for_each_in_table(key, value, error, &mut |key, id, value, error| {
+                    for_each_in_table(key, value, error, &mut |key, name, value, error| {
+                        let entry = match registry::find(id).filter(|entry| entry.is_runnable()) {
+                            Some(entry) => entry,
+                            None => return error(key, format!("unknown operation \"{}\"", id)),
+                        };
+                        match entry.settings.iter().find(|setting| setting.key == name) {
+                            Some(setting) => {
+                                if let Err(message) = setting.set(config, value) {
+                                    error(key, message);
+                                }
+                            }
+                            None => error(key, String::from("unknown setting for this operation")),
+                        }
+                    });
+                });
+            }
+            _ => error(key, String::from("unknown key")),
+        }
+    }
+}
+
+// Calls f with the full key path, name and value of every entry in a table
+fn for_each_in_table(
+    key: &str,
+    value: &Value,
+    error: &mut ErrorFn<'_>,
+    f: &mut dyn FnMut(&str, &str, &Value, &mut ErrorFn<'_>),
+) {
+    match value.as_table() {
+        Some(table) => {
+            for (name, value) in table {
+                f(&format!("{}.{}", key, name), name, value, error);
+            }
+        }
+        None => error(key, String::from("expected a table")),
+    }
+}
+
+fn string_list(key: &str, value: &Value, error: &mut ErrorFn<'_>) -> Option<Vec<String>> {
+    strings(value).map_err(|message| error(key, message)).ok()
+}
+
+pub fn strings(value: &Value) -> Result<Vec<String>, String> {
+    value
+        .as_array()
+        .and_then(|array| array.iter().map(|item| item.as_str().map(String::from)).collect::<Option<Vec<_>>>())
+        .ok_or_else(|| String::from("expected a list of strings"))
+}
+
+fn operation_list(key: &str, value: &Value, error: &mut ErrorFn<'_>) -> Option<Vec<String>> {
+    let ids = string_list(key, value, error)?;
+    for id in &ids {
+        if !registry::is_runnable(id) {
+            error(key, format!("unknown operation \"{}\", run `debloater run` to list them", id));
+        }
+    }
+    Some(ids)
+}
+
+fn set_number(key: &str, value: &Value, min: u64, max: u64, target: &mut u64, error: &mut ErrorFn<'_>) {
+    match number(value, min, max) {
+        Ok(number) => *target = number,
+        Err(message) => error(key, message),
+    }
+}
+
+pub fn number(value: &Value, min: u64, max: u64) -> Result<u64, String> {
+    match value.as_integer().and_then(|number| u64::try_from(number).ok()) {
+        Some(number) if (min..=max).contains(&number) => Ok(number),
+        _ => Err(format!("expected a whole number from {} to {}", min, max)),
+    }
+}
+
+pub fn journal_time(value: &Value) -> Result<String, String> {
+    match value.as_str() {
+        Some(time) if is_journal_time(time) => Ok(time.to_string()),
+        _ => Err(String::from("expected a journalctl time span like \"1d\", \"12h\" or \"2weeks\"")),
+    }
+}
+
+// journalctl time spans: a number, optionally followed by a unit, eg. "1d", "12h" or "2weeks"
+fn is_journal_time(time: &str) -> bool {
+    const UNITS: [&str; 23] = [
+        "", "s", "sec", "second", "seconds", "m", "min", "minute", "minutes", "h", "hour", "hours",
+        "d", "day", "days", "w", "week", "weeks", "month", "months", "y", "year", "years",
+    ];
+    let digits = time.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && UNITS.contains(&time[digits..].trim_start())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+pub fn is_profile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Adds a profile to the end of the user config, leaving the rest of the file, comments included, as it was
+pub fn save_profile(name: &str, operations: &[String]) -> Result<PathBuf, String> {
+    if !is_profile_name(name) {
+        return Err(String::from("Profile names can only contain letters, digits, - and _"));
+    }
+    if operations.is_empty() {
+        return Err(String::from("Nothing is selected that a profile can run"));
+    }
+    let path = user_config_path().ok_or_else(|| String::from("Could not find the config directory, is $HOME set?"))?;
+    let existing = match fs::read_to_string(&path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let table = existing.parse::<Table>().map_err(|e| format!("{}: {}", path.display(), e.to_string().trim_end()))?;
+    match table.get("profiles") {
+        Some(Value::Table(profiles)) if profiles.contains_key(name) => {
+            return Err(format!("{} already has a profile named {}", path.display(), name));
+        }
+        Some(Value::Table(_)) | None => {}
+        Some(_) => return Err(format!("{}: profiles: expected a table", path.display())),
+    }
+
+    let mut profile = Table::new();
+    profile.insert(String::from("operations"), Value::Array(operations.iter().cloned().map(Value::String).collect()));
+    let mut profiles = Table::new();
+    profiles.insert(name.to_string(), Value::Table(profile));
+    let mut root = Table::new();
+    root.insert(String::from("profiles"), Value::Table(profiles));
+    let toml = toml::to_string(&root).map_err(|e| e.to_string())?;
+
+    let separator = if existing.is_empty() || existing.ends_with("\n\n") { "" } else if existing.ends_with('\n') { "\n" } else { "\n\n" };
+    let combined = format!("{}{}{}", existing, separator, toml);
+    // A [profiles.NAME] section can't extend profiles written as an inline table, among others
+    if let Err(e) = combined.parse::<Table>() {
+        return Err(format!("Could not add a [profiles.{}] section to {}: {}", name, path.display(), e.to_string().trim_end()));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, combined).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The config a file sets up on top of the defaults, and the keys of the problems found in it
+    fn apply_str(toml: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut errors = Vec::new();
+        apply(&toml.parse::<Table>().unwrap(), &mut config, &mut |key, message| errors.push(format!("{}: {}", key, message)));
+        (config, errors)
+    }
+
+    #[test]
+    fn applies_settings() {
+        let (config, errors) = apply_str(
+            "excluded_paths = [\"/srv/keep\"]\n\
+             [quarantine]\nretention_days = 36500\n\
+             [operations.clean-package-cache]\nkeep = 1000\n\
+             [operations.empty-trash]\nmin_age_days = 0\n",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(config.excluded_paths, [PathBuf::from("/srv/keep")]);
+        assert_eq!(config.quarantine_retention_days, MAX_DAYS);
+        assert_eq!(config.package_cache_keep, 1000);
+        assert_eq!(config.trash_min_age_days, 0);
+    }
+
+    #[test]
+    fn reports_unknown_keys() {
+        let (_, errors) = apply_str("colour = \"red\"\n[quarantine]\ndays = 1\n[operations.not-an-operation]\nkeep = 1\n[operations.empty-trash]\nkeep = 1\n");
+        assert_eq!(
+            errors,
+            [
+                "colour: unknown key",
+                "operations.empty-trash.keep: unknown setting for this operation",
+                "operations.not-an-operation.keep: unknown operation \"not-an-operation\"",
+                "quarantine.days: unknown key",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_numbers_out_of_range() {
+        let (config, errors) = apply_str(
+            "[quarantine]\nretention_days = 0\n\
+             [operations.clean-package-cache]\nkeep = 1001\n\
+             [operations.empty-trash]\nmin_age_days = 36501\n\
+             [operations.clean-user-cache]\nmin_age_days = -1\n",
+        );
+        assert_eq!(
+            errors,
+            [
+                "operations.clean-package-cache.keep: expected a whole number from 0 to 1000",
+                "operations.clean-user-cache.min_age_days: expected a whole number from 0 to 36500",
+                "operations.empty-trash.min_age_days: expected a whole number from 0 to 36500",
+                "quarantine.retention_days: expected a whole number from 1 to 36500",
+            ]
+        );
+        // Bad values leave the defaults alone
+        assert_eq!(config.quarantine_retention_days, quarantine::DEFAULT_RETENTION_DAYS);
+        assert_eq!(config.trash_min_age_days, trash::DEFAULT_MIN_AGE_DAYS);
+    }
+
+    #[test]
+    fn reports_bad_values() {
+        let (config, errors) = apply_str("escalation = \"su\"\ntheme = 3\nexcluded_paths = [\"/srv/keep\", \"relative/path\"]\n[profiles.\"bad name\"]\noperations = []\n");
+        assert_eq!(
+            errors,
+            [
+                "escalation: unknown tool \"su\", expected sudo, doas, run0 or pkexec",
+                "excluded_paths: \"relative/path\" is not an absolute path, start it with / or ~/",
+                "profiles.bad name: profile names can only contain letters, digits, - and _",
+                "theme: expected a string",
+            ]
+        );
+        assert!(config.excluded_paths.is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use crate::filesystem::mounts;
+
+// Read-only images that are always full, eg. snaps and live media
+const IGNORED_FS_TYPES: [&str; 3] = ["squashfs", "iso9660", "erofs"];
+
+pub struct MountUsage {
+    pub mount_point: PathBuf,
+    pub device: String,
+    pub fs_type: String,
+    pub total: u64,
+    pub used: u64,
+    pub available: u64, // To unprivileged users, which excludes the blocks reserved for root
+}
+
+impl MountUsage {
+    // Like df: the share of the space available to users that is taken
+    pub fn used_ratio(&self) -> f64 {
+        let usable = self.used + self.available;
+        if usable == 0 { 0.0 } else { self.used as f64 / usable as f64 }
+    }
+}
+
+pub struct FileNode {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub is_dir: bool,
+    pub children: Vec<FileNode>, // Sorted by size, largest first
+}
+
+// Builds a size tree of everything under root, staying on root's filesystem (like `du -x`) so that scanning /
+// doesn't wander into /proc or other mounts. The top-level entries are spread across worker threads.
+pub fn scan(root: &Path) -> Result<FileNode, String> {
+    let metadata = fs::symlink_metadata(root).map_err(|e| format!("{}: {}", root.display(), e))?;
+    let device = metadata.dev();
+    if !metadata.is_dir() {
+        return Ok(scan_node(root, device, &Mutex::new(HashSet::new())));
+    }
+
+    let entries: Vec<PathBuf> = fs::read_dir(root)
+        .map_err(|e| format!("{}: {}", root.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+
+    let queue = Mutex::new(entries.iter());
+    let seen = Mutex::new(HashSet::new());
+    let children = Mutex::new(Vec::with_capacity(entries.len()));
+    let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some(path) => {
+                        let node = scan_node(path, device, &seen);
+                        children.lock().unwrap().push(node);
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+
+    let mut children = children.into_inner().unwrap();
+    sort_by_size(&mut children);
+    Ok(FileNode {
+        name: root.display().to_string(),
+        path: root.to_path_buf(),
+        size: children.iter().map(|child| child.size).sum(),
+        is_dir: true,
+        children,
+    })
+}
+
+// Sizes are the space allocated on disk, like du: sparse files count for less than their length, and a file
+// with several hardlinks only counts at the first one seen, tracked by (device, inode) in seen
+fn scan_node(path: &Path, device: u64, seen: &Mutex<HashSet<(u64, u64)>>) -> FileNode {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string());
+    let metadata = fs::symlink_metadata(path);
+    let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+    let mut node = FileNode { name, path: path.to_path_buf(), size: 0, is_dir, children: Vec::new() };
+
+    match metadata {
+        Ok(metadata) if is_dir && metadata.dev() == device => {
+            if let Ok(entries) = fs::read_dir(path) {
+                node.children = entries.flatten().map(|entry| scan_node(&entry.path(), device, seen)).collect();
+                sort_by_size(&mut node.children);
+                node.size = node.children.iter().map(|child| child.size).sum();
+            }
+        }
+        Ok(metadata) if !is_dir && (metadata.nlink() <= 1 || seen.lock().unwrap().insert((metadata.dev(), metadata.ino()))) => {
+            node.size = metadata.blocks() * 512;
+        }
+        _ => {}
+    }
+    node
+}
+
+fn sort_by_size(nodes: &mut [FileNode]) {
+    nodes.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+}
+
+// Usage of every filesystem backed by a disk. A device mounted more than once, eg. btrfs subvolumes, is only
+// listed at its first mount point.
+pub fn mount_usage() -> Vec<MountUsage> {
+    let mut usage: Vec<MountUsage> = Vec::new();
+    for mount in mounts() {
+        let on_disk = mount.device.starts_with('/') || mount.fs_type == "zfs";
+        if !on_disk || IGNORED_FS_TYPES.contains(&mount.fs_type.as_str()) || usage.iter().any(|u| u.device == mount.device) {
+            continue;
+        }
+        if let Some((total, used, available)) = statvfs(&mount.mount_point) {
+            if total > 0 {
+                usage.push(MountUsage { mount_point: mount.mount_point, device: mount.device, fs_type: mount.fs_type, total, used, available });
+            }
+        }
+    }
+    usage
+}
+
+// Total, used and available bytes of the filesystem holding path
+fn statvfs(path: &Path) -> Option<(u64, u64, u64)> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    let total = stat.f_blocks as u64 * block;
+    let used = (stat.f_blocks as u64).saturating_sub(stat.f_bfree as u64) * block;
+    Some((total, used, stat.f_bavail as u64 * block))
+}
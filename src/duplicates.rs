@@ -0,0 +1,167 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use crate::filesystem::path_error;
+
+// Bytes hashed from the start of each file before committing to a full hash
+const PARTIAL_HASH_BYTES: u64 = 16 * 1024;
+
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DedupAction {
+    Delete,
+    Hardlink,
+}
+
+pub struct DuplicateResolution {
+    pub keep: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+    pub action: DedupAction,
+    pub size: u64,
+}
+
+// Finds groups of identical regular files under the given roots. Candidates are narrowed down by size, then by a
+// hash of their first few KiB, and only then fully hashed. Files that are already hardlinked together count once.
+pub fn find_duplicates(roots: &[PathBuf]) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut seen_inodes = HashSet::new();
+    for root in roots {
+        collect_files(root, &mut by_size, &mut seen_inodes);
+    }
+
+    let mut groups = Vec::new();
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        for partial_group in bucket_by(paths, |path| hash_file(path, Some(PARTIAL_HASH_BYTES))) {
+            // Files no bigger than the partial hash were already hashed in full
+            let full_groups = if size <= PARTIAL_HASH_BYTES {
+                vec![partial_group]
+            } else {
+                bucket_by(partial_group, |path| hash_file(path, None))
+            };
+            for mut paths in full_groups {
+                paths.sort();
+                groups.push(DuplicateGroup { size, paths });
+            }
+        }
+    }
+
+    // Biggest savings first
+    groups.sort_by(|a, b| {
+        let wasted = |group: &DuplicateGroup| group.size * (group.paths.len() as u64 - 1);
+        wasted(b).cmp(&wasted(a)).then_with(|| a.paths.cmp(&b.paths))
+    });
+    groups
+}
+
+fn collect_files(path: &Path, by_size: &mut HashMap<u64, Vec<PathBuf>>, seen_inodes: &mut HashSet<(u64, u64)>) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                collect_files(&entry.path(), by_size, seen_inodes);
+            }
+        }
+    } else if metadata.is_file() && metadata.len() > 0 && seen_inodes.insert((metadata.dev(), metadata.ino())) {
+        by_size.entry(metadata.len()).or_default().push(path.to_path_buf());
+    }
+}
+
+// Splits paths into groups sharing the same key, dropping groups of one and unreadable files
+fn bucket_by(paths: Vec<PathBuf>, key: impl Fn(&Path) -> io::Result<u64>) -> Vec<Vec<PathBuf>> {
+    let mut buckets: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        if let Ok(hash) = key(&path) {
+            buckets.entry(hash).or_default().push(path);
+        }
+    }
+    buckets.into_values().filter(|bucket| bucket.len() > 1).collect()
+}
+
+fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<u64> {
+    let file = File::open(path)?;
+    let mut reader: Box<dyn Read> = match limit {
+        Some(limit) => Box::new(BufReader::new(file).take(limit)),
+        None => Box::new(BufReader::new(file)),
+    };
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+// Byte for byte comparison, used right before removing anything so that a hash collision can never cost data
+pub fn files_identical(a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let (mut a, mut b) = (BufReader::new(File::open(a)?), BufReader::new(File::open(b)?));
+    let (mut buffer_a, mut buffer_b) = ([0u8; 64 * 1024], [0u8; 64 * 1024]);
+    loop {
+        let read = a.read(&mut buffer_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buffer_b[..read])?;
+        if buffer_a[..read] != buffer_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+// Applies a resolution, returning the bytes freed
+pub fn resolve(resolution: &DuplicateResolution) -> Result<u64, Vec<io::Error>> {
+    let mut freed = 0;
+    let mut errors = Vec::new();
+    for duplicate in &resolution.duplicates {
+        match resolve_one(&resolution.keep, duplicate, resolution.action) {
+            Ok(()) => freed += resolution.size,
+            Err(e) => errors.push(path_error(duplicate, e)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(freed)
+    } else {
+        Err(errors)
+    }
+}
+
+fn resolve_one(keep: &Path, duplicate: &Path, action: DedupAction) -> io::Result<()> {
+    if !files_identical(keep, duplicate)? {
+        return Err(io::Error::other(format!("no longer identical to {}, skipped", keep.display())));
+    }
+    match action {
+        DedupAction::Delete => fs::remove_file(duplicate),
+        DedupAction::Hardlink => {
+            // Link under a temporary name first, so the duplicate is only replaced once the link exists
+            let file_name = duplicate.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?.to_string_lossy();
+            let temporary = duplicate.with_file_name(format!(".{}.debloater-link", file_name));
+            fs::hard_link(keep, &temporary).map_err(|e| match e.raw_os_error() {
+                Some(libc::EXDEV) => io::Error::new(e.kind(), format!("cannot hardlink across filesystems to {}", keep.display())),
+                _ => e,
+            })?;
+            fs::rename(&temporary, duplicate).inspect_err(|_| {
+                let _ = fs::remove_file(&temporary);
+            })
+        }
+    }
+}
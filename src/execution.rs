@@ -0,0 +1,291 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+use crate::filesystem::{find_in_path, format_size};
+use crate::history::SnapshotRecord;
+use crate::privilege;
+
+// Programs that run another program as root, see privilege.rs
+const ESCALATION_TOOLS: [&str; 4] = ["sudo", "doas", "run0", "pkexec"];
+
+#[derive(Clone, Debug)]
+pub enum OperationError {
+    CommandMissing(String),
+    PermissionDenied(String),
+    LockHeld(String),
+    Unavailable(String), // A prerequisite other than a program, eg. a service that isn't running
+    NonZeroExit { command: String, code: Option<i32>, stderr: String },
+    Cancelled,
+    Failed(String),
+}
+
+impl fmt::Display for OperationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CommandMissing(program) => write!(f, "{} is not installed", program),
+            Self::PermissionDenied(message) => write!(f, "Permission denied: {}", message),
+            Self::LockHeld(message) => write!(f, "{}", message),
+            Self::Unavailable(message) => write!(f, "{}", message),
+            Self::NonZeroExit { command, code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "{} exited with code {}", command, code)?,
+                    None => write!(f, "{} was killed", command)?,
+                }
+                match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                    Some(line) => write!(f, ": {}", line.trim()),
+                    None => Ok(()),
+                }
+            }
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl OperationError {
+    // Classifies an error from starting program
+    pub fn spawn_failed(program: &str, error: io::Error) -> OperationError {
+        match error.kind() {
+            io::ErrorKind::NotFound => Self::CommandMissing(program.to_string()),
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(format!("{}: {}", program, error)),
+            _ => Self::Failed(format!("{}: {}", program, error)),
+        }
+    }
+
+    // Classifies the errors of a helper that worked through several paths by the kind of the io errors
+    pub fn from_io(errors: Vec<io::Error>) -> OperationError {
+        let message = errors.iter().map(io::Error::to_string).collect::<Vec<_>>().join("\n");
+        if errors.iter().any(|e| e.kind() == io::ErrorKind::PermissionDenied) {
+            Self::PermissionDenied(message)
+        } else {
+            Self::Failed(message)
+        }
+    }
+
+    // Stable name of the kind of error, for JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::CommandMissing(_) => "command_missing",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::LockHeld(_) => "lock_held",
+            Self::Unavailable(_) => "unavailable",
+            Self::NonZeroExit { .. } => "non_zero_exit",
+            Self::Cancelled => "cancelled",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+pub enum Outcome {
+    Succeeded,
+    Failed(OperationError),
+    Skipped(OperationError), // Not run, with the reason why
+}
+
+pub struct OperationResult {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub bytes_freed: u64,
+    pub items_removed: usize,
+    pub duration: Duration,
+    pub commands: Vec<String>,
+    pub stderr: String,
+    pub warnings: Vec<String>,
+}
+
+impl OperationResult {
+    pub fn skipped(name: &'static str, reason: OperationError) -> OperationResult {
+        OperationContext::new(name, None).finish(Outcome::Skipped(reason), Duration::ZERO)
+    }
+
+    // One line describing how it went, eg. "Clean user cache: succeeded in 1.2s, removed 12 items (340.0 MiB)"
+    pub fn summary(&self) -> String {
+        let status = match &self.outcome {
+            Outcome::Succeeded => String::from("succeeded"),
+            Outcome::Failed(e) => format!("failed ({})", e),
+            Outcome::Skipped(e) => return format!("{}: skipped ({})", self.name, e),
+        };
+        let mut summary = format!("{}: {} in {:.1}s", self.name, status, self.duration.as_secs_f64());
+        if self.items_removed > 0 || self.bytes_freed > 0 {
+            summary.push_str(&format!(", removed {} items ({})", self.items_removed, format_size(self.bytes_freed)));
+        }
+        if !self.commands.is_empty() {
+            summary.push_str(&format!(", ran {}", self.commands.join("; ")));
+        }
+        summary
+    }
+}
+
+// What a batch of operations running in the background reports back to the screen showing it
+pub enum ExecutionEvent {
+    Started(usize),
+    Output(String),
+    Finished(usize, OperationResult),
+    Snapshot(SnapshotRecord), // Taken around the package operations, sent once they are all done
+    Done,
+}
+
+// Where operations write their output to, one line at a time
+#[derive(Clone)]
+pub struct Log {
+    sender: Sender<ExecutionEvent>,
+}
+
+impl Log {
+    pub fn new(sender: Sender<ExecutionEvent>) -> Log {
+        Log { sender }
+    }
+
+    pub fn line(&self, line: impl Into<String>) {
+        // Nobody is listening any more once the screen is gone, which is fine
+        let _ = self.sender.send(ExecutionEvent::Output(line.into()));
+    }
+
+    pub fn send(&self, event: ExecutionEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+// Handed to an operation while it runs, collecting what goes into its OperationResult
+pub struct OperationContext {
+    name: &'static str,
+    log: Option<Log>,
+    bytes_freed: u64,
+    items_removed: usize,
+    commands: Vec<String>,
+    stderr: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl OperationContext {
+    pub fn new(name: &'static str, log: Option<Log>) -> OperationContext {
+        OperationContext {
+            name,
+            log,
+            bytes_freed: 0,
+            items_removed: 0,
+            commands: Vec::new(),
+            stderr: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn line(&self, line: impl Into<String>) {
+        if let Some(log) = &self.log {
+            log.line(line);
+        }
+    }
+
+    pub fn warn(&mut self, warning: impl Into<String>) {
+        let warning = warning.into();
+        self.line(format!("Warning: {}", warning));
+        self.warnings.push(warning);
+    }
+
+    pub fn removed(&mut self, items: usize, bytes: u64) {
+        self.items_removed += items;
+        self.bytes_freed += bytes;
+    }
+
+    // Runs a command, passing its stdout and stderr on to the log as they are written. Nothing can be typed
+    // into it, so commands that would ask a question have to be told not to.
+    pub fn run(&mut self, command: &mut Command) -> Result<(), OperationError> {
+        let program = command.get_program().to_string_lossy().into_owned();
+        let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        let command_line = std::iter::once(program.clone()).chain(args.iter().cloned()).collect::<Vec<_>>().join(" ");
+        self.commands.push(command_line.clone());
+
+        // Behind sudo and friends a missing program only shows up as a failed run, so look for it first
+        let target = if ESCALATION_TOOLS.contains(&program.as_str()) {
+            args.iter().find(|arg| !arg.starts_with('-')).cloned().unwrap_or_else(|| program.clone())
+        } else {
+            program.clone()
+        };
+        if find_in_path(&target).is_none() {
+            return Err(OperationError::CommandMissing(target));
+        }
+
+        // Batches that authenticated once run their root commands through a session
+        let mut errors = Vec::new();
+        let log = self.log.as_ref();
+        let session = privilege::run_in_session(command, &mut |line, from_stderr| {
+            let lines = forward_line(log, line);
+            if from_stderr {
+                errors.extend(lines);
+            }
+        });
+        let code = match session {
+            Some(result) => result.map_err(|e| OperationError::Failed(format!("{}: {}", program, e)))?,
+            None => {
+                let mut child = command
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| OperationError::spawn_failed(&program, e))?;
+
+                let stderr = child.stderr.take().map(|stderr| {
+                    let log = self.log.clone();
+                    thread::spawn(move || forward_lines(log.as_ref(), stderr))
+                });
+                if let Some(stdout) = child.stdout.take() {
+                    forward_lines(self.log.as_ref(), stdout);
+                }
+                errors = stderr.and_then(|handle| handle.join().ok()).unwrap_or_default();
+                child.wait().map_err(|e| OperationError::Failed(format!("{}: {}", program, e)))?.code()
+            }
+        };
+        self.stderr.extend(errors.iter().cloned());
+        if code == Some(0) {
+            return Ok(());
+        }
+        let stderr = errors.join("\n");
+        let lowered = stderr.to_lowercase();
+        Err(if lowered.contains("unable to lock database") {
+            OperationError::LockHeld(stderr)
+        } else if lowered.contains("permission denied") || lowered.contains("a password is required") || lowered.contains("not authorized") {
+            OperationError::PermissionDenied(stderr)
+        } else {
+            OperationError::NonZeroExit { command: command_line, code, stderr }
+        })
+    }
+
+    pub fn finish(self, outcome: Outcome, duration: Duration) -> OperationResult {
+        OperationResult {
+            name: self.name,
+            outcome,
+            bytes_freed: self.bytes_freed,
+            items_removed: self.items_removed,
+            duration,
+            commands: self.commands,
+            stderr: self.stderr.join("\n"),
+            warnings: self.warnings,
+        }
+    }
+}
+
+fn forward_lines(log: Option<&Log>, stream: impl Read) -> Vec<String> {
+    BufReader::new(stream)
+        .split(b'\n')
+        .map_while(Result::ok)
+        .flat_map(|chunk| forward_line(log, &String::from_utf8_lossy(&chunk)))
+        .collect()
+}
+
+// Progress bars redraw themselves with carriage returns, which are treated as line ends too
+fn forward_line(log: Option<&Log>, line: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in line.split('\r') {
+        let line = line.trim_end();
+        if !line.is_empty() {
+            if let Some(log) = log {
+                log.line(line);
+            }
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
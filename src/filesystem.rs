@@ -0,0 +1,158 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Total size in bytes of a file or directory tree. Symlinks are not followed and unreadable entries are skipped.
+pub fn dir_size(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    let mut total = 0;
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            total += dir_size(&entry.path());
+        }
+    }
+    total
+}
+
+// Removes a file, symlink or directory tree
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| path_error(path, e))?;
+    let result = if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.map_err(|e| path_error(path, e))
+}
+
+// Puts the path an io error is about in its message, keeping its kind for the caller to classify
+pub fn path_error(path: &Path, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+// Human readable size, eg. "1.4 GiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+// Resolves an XDG base directory, falling back to the given path under $HOME
+pub fn xdg_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    match std::env::var_os(variable) {
+        Some(dir) if Path::new(&dir).is_absolute() => Some(PathBuf::from(dir)),
+        _ => home_dir().map(|home| home.join(fallback)),
+    }
+}
+
+// Whether a file name matches a pattern in which * stands for any run of characters, eg. "mozilla*"
+pub fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap_or("")) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+// Looks a binary up in $PATH, like `which`
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).map(|dir| dir.join(binary)).find(|candidate| candidate.is_file())
+}
+
+// Mount points of all currently mounted filesystems, read from /proc/self/mounts
+pub fn mount_points() -> Vec<PathBuf> {
+    mounts().into_iter().map(|mount| mount.mount_point).collect()
+}
+
+pub struct Mount {
+    pub device: String, // eg. /dev/nvme0n1p2, or tmpfs for virtual filesystems
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+// All currently mounted filesystems, in the order of /proc/self/mounts
+pub fn mounts() -> Vec<Mount> {
+    let contents = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(Mount {
+                device: unescape_mount_path(fields.next()?),
+                mount_point: PathBuf::from(unescape_mount_path(fields.next()?)),
+                fs_type: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+// /proc/self/mounts escapes spaces, tabs, newlines and backslashes as octal sequences (eg. \040)
+fn unescape_mount_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let value = u32::from(bytes[i + 1] - b'0') * 64 + u32::from(bytes[i + 2] - b'0') * 8 + u32::from(bytes[i + 3] - b'0');
+            result.push(value as u8);
+            i += 4;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches_wildcard("mozilla", "mozilla"));
+        assert!(!matches_wildcard("mozilla", "mozilla-old"));
+        assert!(matches_wildcard("mozilla*", "mozilla"));
+        assert!(matches_wildcard("mozilla*", "mozilla-old"));
+        assert!(matches_wildcard("*cache", "thumbcache"));
+        assert!(matches_wildcard("a*b*c", "aXbYc"));
+        assert!(matches_wildcard("*", ""));
+        assert!(!matches_wildcard("a*b*c", "aXcYb"));
+        assert!(!matches_wildcard("*cache", "cache-old"));
+    }
+
+    #[test]
+    fn wildcards_never_match_a_part_twice() {
+        // The prefix and suffix can't share the same characters
+        assert!(!matches_wildcard("ab*ba", "aba"));
+        assert!(matches_wildcard("ab*ba", "abba"));
+        assert!(!matches_wildcard("a*a*a", "aa"));
+    }
+}
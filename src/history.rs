@@ -0,0 +1,109 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::execution::{OperationResult, Outcome};
+use crate::filesystem::xdg_dir;
+use crate::system;
+use crate::timestamp;
+
+// One JSON record per line, appended after every run
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Serialize, Deserialize)]
+pub struct OperationRecord {
+    pub name: String,
+    pub outcome: String, // "succeeded", "failed" or "skipped"
+    #[serde(default)]
+    pub error: Option<String>,
+    pub bytes_freed: u64,
+    pub items_removed: usize,
+    pub duration_ms: u64,
+}
+
+impl From<&OperationResult> for OperationRecord {
+    fn from(result: &OperationResult) -> OperationRecord {
+        let (outcome, error) = match &result.outcome {
+            Outcome::Succeeded => ("succeeded", None),
+            Outcome::Failed(e) => ("failed", Some(e.to_string())),
+            Outcome::Skipped(e) => ("skipped", Some(e.to_string())),
+        };
+        OperationRecord {
+            name: result.name.to_string(),
+            outcome: outcome.to_string(),
+            error,
+            bytes_freed: result.bytes_freed,
+            items_removed: result.items_removed,
+            duration_ms: result.duration.as_millis() as u64,
+        }
+    }
+}
+
+// The snapshot taken before a run's package operations, and the one after them for tools that pair them
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SnapshotRecord {
+    pub tool: String, // "snapper" or "timeshift"
+    pub pre_id: String,
+    pub post_id: Option<String>,
+}
+
+impl SnapshotRecord {
+    pub fn describe(&self) -> String {
+        match &self.post_id {
+            Some(post_id) => format!("{} {} (before), {} (after)", self.tool, self.pre_id, post_id),
+            None => format!("{} {}", self.tool, self.pre_id),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RunRecord {
+    pub timestamp: u64,
+    pub hostname: String,
+    pub distro: String,
+    pub operations: Vec<OperationRecord>,
+    pub bytes_freed: u64,
+    #[serde(default)]
+    pub snapshot: Option<SnapshotRecord>,
+}
+
+impl RunRecord {
+    pub fn new(operations: Vec<OperationRecord>, snapshot: Option<SnapshotRecord>) -> RunRecord {
+        RunRecord {
+            timestamp: timestamp::now(),
+            hostname: system::hostname(),
+            distro: system::distribution_id().unwrap_or_else(|| String::from("unknown")),
+            bytes_freed: operations.iter().map(|operation| operation.bytes_freed).sum(),
+            operations,
+            snapshot,
+        }
+    }
+
+    pub fn count(&self, outcome: &str) -> usize {
+        self.operations.iter().filter(|operation| operation.outcome == outcome).count()
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state").map(|state| state.join("debloater").join(HISTORY_FILE))
+}
+
+pub fn append(record: &RunRecord) -> Result<(), String> {
+    let path = history_path().ok_or_else(|| String::from("Could not find the state directory, is $HOME set?"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// All recorded runs, oldest first. Lines that don't parse, eg. from a run killed mid-write, are skipped.
+pub fn load() -> Vec<RunRecord> {
+    let contents = history_path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or_default();
+    contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
@@ -1,26 +1,32 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::io;
 use colored::Colorize;
 
 mod arch;
+mod browsers;
+mod cli;
+mod config;
+mod disk_usage;
+mod duplicates;
 // mod debian;
 // mod fedora;
-mod operation_descriptions;
+mod execution;
+mod filesystem;
+mod history;
+mod privilege;
+mod quarantine;
+mod report;
+mod schedule;
+mod snapshots;
+mod system;
+mod terminal;
+mod theme;
+mod thumbnails;
+mod timestamp;
+mod trash;
 
 fn get_distribution() -> Option<String> {
     println!("{}", "Detecting distribution...".bold());
-    let file = File::open("/etc/os-release").ok()?; // opens /etc/release
-    let reader = BufReader::new(file);
-    
-    for line in reader.lines() { // Searches each line of the file for the 'ID=' tag
-        if let Ok(line) = line {
-            if (line.starts_with("ID=")) {
-                return Some(line.replace("ID=", "").replace("\"", ""));
-            }
-        }
-    }
-    None
+    system::distribution_id()
 }
 
 fn handle_debian() {
@@ -44,6 +50,41 @@ fn handle_fedora() {
 }
 
 fn main() {
+    // Started as root by privilege::begin_batch, which reads no config
+    if std::env::args().nth(1).as_deref() == Some(privilege::HELPER_COMMAND) {
+        if let Err(e) = privilege::serve() {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(errors) = config::init() {
+        for error in errors {
+            eprintln!("{} {}", "Config error:".red().bold(), error);
+        }
+        std::process::exit(1);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        let result = match command.as_str() {
+            "undo" => cli::undo(&args[1..]),
+            "history" => cli::history(),
+            "run" => cli::run(&args[1..]),
+            "profile" => cli::profile(&args[1..]),
+            "install-schedule" => cli::install_schedule(&args[1..]),
+            "uninstall-schedule" => cli::uninstall_schedule(&args[1..]),
+            "status" => cli::status(),
+            _ => Err(format!("Unknown command: {}", command)),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let distro = get_distribution().unwrap_or_else(|| String::from("unknown")); // Grabs the distribution ID
     
     match distro.as_str() {
@@ -0,0 +1,351 @@
+use std::io::{self, BufRead, BufReader, Read, Stdout, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::filesystem::find_in_path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Escalation {
+    // Already running as root
+    None,
+    Sudo,
+    Doas,
+    Run0,
+    Pkexec,
+}
+
+static ESCALATION: OnceLock<Escalation> = OnceLock::new();
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+// Argument that starts debloater as the root helper of a session
+pub const HELPER_COMMAND: &str = "root-helper";
+
+// How often sudo's timestamp is refreshed, well within its default 5 minute timeout
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Escalation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Sudo => "sudo",
+            Self::Doas => "doas",
+            Self::Run0 => "run0",
+            Self::Pkexec => "pkexec",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Escalation> {
+        [Self::Sudo, Self::Doas, Self::Run0, Self::Pkexec].into_iter().find(|tool| tool.name() == name)
+    }
+
+    // Root needs nothing. Otherwise the preferred tool is used if it is installed, then the first one found.
+    pub fn detect(preferred: Option<Escalation>) -> Option<Escalation> {
+        if unsafe { libc::geteuid() } == 0 {
+            return Some(Self::None);
+        }
+        preferred
+            .into_iter()
+            .chain([Self::Sudo, Self::Doas, Self::Run0, Self::Pkexec])
+            .find(|tool| find_in_path(tool.name()).is_some())
+    }
+
+    // Asks for the password once, with the terminal in its normal mode so the prompt is visible. Commands run
+    // afterwards reuse the cached credentials where the tool supports it.
+    pub fn authenticate(&self) -> Result<(), String> {
+        if *self == Self::None {
+            return Ok(());
+        }
+        let mut command = Command::new(self.name());
+        match self {
+            Self::Sudo => command.arg("-v"),
+            _ => command.arg("true"),
+        };
+        let status = command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .map_err(|e| format!("{}: {}", self.name(), e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("Authentication with {} failed", self.name()))
+        }
+    }
+
+    // Whether commands run after authenticating reuse the credentials. Only sudo's are sure to be: doas keeps them
+    // only with `persist` in doas.conf, which a user can't always read, and run0 and pkexec ask polkit every time.
+    pub fn caches_credentials(&self) -> bool {
+        matches!(self, Self::None | Self::Sudo)
+    }
+
+    // Builds a command running program as root
+    pub fn command(&self, program: &str) -> Command {
+        match self {
+            Self::None => Command::new(program),
+            // Non-interactive: with output captured a password prompt would be invisible and hang the run,
+            // so fail instead if the cached credentials ran out
+            Self::Sudo => {
+                let mut command = Command::new(self.name());
+                command.args(["-n", program]);
+                command
+            }
+            // These only ask on a terminal, and fail without one. Batches run them through a session.
+            Self::Doas | Self::Run0 | Self::Pkexec => {
+                let mut command = Command::new(self.name());
+                command.arg(program);
+                command
+            }
+        }
+    }
+
+    // Keeps sudo's cached credentials fresh until the returned guard is dropped. The other tools either
+    // have no timestamp to refresh or handle it through polkit.
+    pub fn keep_alive(&self) -> Option<KeepAlive> {
+        if *self != Self::Sudo {
+            return None;
+        }
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(KEEP_ALIVE_INTERVAL) {
+                let _ = Command::new("sudo").args(["-n", "-v"]).stdout(Stdio::null()).stderr(Stdio::null()).status();
+            }
+        });
+        Some(KeepAlive::Refresh { stop, handle: Some(handle) })
+    }
+}
+
+// Held while a batch of privileged commands runs, stopping whatever begin_batch started once dropped
+pub enum KeepAlive {
+    // Refreshes sudo's cached credentials
+    Refresh { stop: Sender<()>, handle: Option<JoinHandle<()>> },
+    // Every command of the batch runs through the session's root helper
+    Session,
+}
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        match self {
+            Self::Refresh { stop, handle } => {
+                let _ = stop.send(());
+                if let Some(handle) = handle.take() {
+                    let _ = handle.join();
+                }
+            }
+            Self::Session => end_session(),
+        }
+    }
+}
+
+// A copy of debloater started as root through the escalation tool, for the tools that can't reuse credentials.
+// It is authenticated once when the batch begins, then runs every command sent to it until its stdin closes.
+// Only this process holds the other ends of its pipes, so nothing else can send it commands.
+struct Session {
+    child: Child,
+    requests: ChildStdin,
+    replies: BufReader<ChildStdout>,
+}
+
+// What the root helper writes back, one per line
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Reply {
+    Ready,
+    Stdout(String),
+    Stderr(String),
+    Exit(Option<i32>),
+}
+
+impl Session {
+    // Asks for authentication with the terminal in its normal mode, returning once the helper runs as root
+    fn start(escalation: Escalation) -> Result<Session, String> {
+        let exe = std::env::current_exe().map_err(|e| format!("Could not find the debloater executable: {}", e))?;
+        let mut child = Command::new(escalation.name())
+            .arg(exe)
+            .arg(HELPER_COMMAND)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("{}: {}", escalation.name(), e))?;
+        let (Some(requests), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err(format!("Could not talk to {}", escalation.name()));
+        };
+        let mut session = Session { child, requests, replies: BufReader::new(stdout) };
+        match session.reply() {
+            Ok(Reply::Ready) => Ok(session),
+            _ => {
+                session.stop();
+                Err(format!("Authentication with {} failed", escalation.name()))
+            }
+        }
+    }
+
+    fn reply(&mut self) -> io::Result<Reply> {
+        let mut line = String::new();
+        if self.replies.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the root helper exited"));
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    fn run(&mut self, args: &[&str], on_line: &mut dyn FnMut(&str, bool)) -> io::Result<Option<i32>> {
+        writeln!(self.requests, "{}", serde_json::to_string(args)?)?;
+        loop {
+            match self.reply()? {
+                Reply::Stdout(line) => on_line(&line, false),
+                Reply::Stderr(line) => on_line(&line, true),
+                Reply::Exit(code) => return Ok(code),
+                Reply::Ready => {}
+            }
+        }
+    }
+
+    // Closing stdin tells the helper to exit
+    fn stop(self) {
+        let Session { mut child, requests, replies } = self;
+        drop(requests);
+        drop(replies);
+        let _ = child.wait();
+    }
+}
+
+fn end_session() {
+    if let Some(session) = SESSION.lock().unwrap_or_else(PoisonError::into_inner).take() {
+        session.stop();
+    }
+}
+
+// Runs a command built by `command` through the root helper when a batch started one, passing each line of its
+// output to on_line along with whether it came from stderr. Returns None without a helper, otherwise the exit code,
+// which is None if a signal ended the command.
+pub fn run_in_session(command: &Command, on_line: &mut dyn FnMut(&str, bool)) -> Option<io::Result<Option<i32>>> {
+    let mut session = SESSION.lock().unwrap_or_else(PoisonError::into_inner);
+    let session = session.as_mut()?;
+    if command.get_program() != ESCALATION.get()?.name() {
+        return None;
+    }
+    let args: Option<Vec<&str>> = command.get_args().map(|arg| arg.to_str()).collect();
+    let result = match args {
+        Some(args) => {
+            let args: Vec<&str> = args.into_iter().skip_while(|arg| arg.starts_with('-')).collect();
+            session.run(&args, on_line)
+        }
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "arguments that aren't UTF-8 can't be sent to the root helper")),
+    };
+    Some(result)
+}
+
+// The root helper's side of a session: runs each command read from stdin, one JSON list of arguments per line,
+// and writes back its output and exit code
+pub fn serve() -> Result<(), String> {
+    let out = Mutex::new(io::stdout());
+    send(&out, &Reply::Ready);
+    for request in io::stdin().lines() {
+        let request = request.map_err(|e| e.to_string())?;
+        let args: Vec<String> = serde_json::from_str(&request).map_err(|e| e.to_string())?;
+        let code = match args.split_first() {
+            Some((program, args)) => serve_command(program, args, &out),
+            None => None,
+        };
+        send(&out, &Reply::Exit(code));
+    }
+    Ok(())
+}
+
+fn serve_command(program: &str, args: &[String], out: &Mutex<Stdout>) -> Option<i32> {
+    let mut child = match Command::new(program).args(args).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            send(out, &Reply::Stderr(format!("{}: {}", program, e)));
+            return Some(127);
+        }
+    };
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    thread::scope(|scope| {
+        if let Some(stderr) = stderr {
+            scope.spawn(|| send_lines(stderr, out, Reply::Stderr));
+        }
+        if let Some(stdout) = stdout {
+            send_lines(stdout, out, Reply::Stdout);
+        }
+    });
+    child.wait().ok().and_then(|status| status.code())
+}
+
+fn send_lines(stream: impl Read, out: &Mutex<Stdout>, reply: fn(String) -> Reply) {
+    for chunk in BufReader::new(stream).split(b'\n').map_while(Result::ok) {
+        send(out, &reply(String::from_utf8_lossy(&chunk).into_owned()));
+    }
+}
+
+// Nobody is left to tell if writing fails, and the helper exits once its stdin closes anyway
+fn send(out: &Mutex<Stdout>, reply: &Reply) {
+    if let Ok(line) = serde_json::to_string(reply) {
+        let mut out = out.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = writeln!(out, "{}", line).and_then(|_| out.flush());
+    }
+}
+
+// Picks the escalation tool for this run. Only the first call has any effect.
+pub fn init(preferred: Option<Escalation>) -> Result<Escalation, String> {
+    if let Some(escalation) = ESCALATION.get() {
+        return Ok(*escalation);
+    }
+    let escalation = Escalation::detect(preferred)
+        .ok_or_else(|| String::from("None of sudo, doas, run0 or pkexec is installed, run debloater as root instead"))?;
+    Ok(*ESCALATION.get_or_init(|| escalation))
+}
+
+// Picks the escalation tool for a batch of privileged commands run without a terminal, which rules out the tools
+// that ask polkit, as nobody is there to answer
+pub fn init_batch(preferred: Option<Escalation>) -> Result<Escalation, String> {
+    let escalation = init(preferred)?;
+    if !matches!(escalation, Escalation::Run0 | Escalation::Pkexec) {
+        Ok(escalation)
+    } else {
+        Err(format!(
+            "{} asks for authentication, which can't be answered without a terminal. Set escalation to sudo or doas in the config, or run debloater as root",
+            escalation.name()
+        ))
+    }
+}
+
+// Authenticates once for a batch of privileged commands, with the terminal in its normal mode. sudo's credentials
+// are kept alive for the batch, the other tools start a session running its commands.
+pub fn begin_batch(preferred: Option<Escalation>) -> Result<Option<KeepAlive>, String> {
+    let escalation = init(preferred)?;
+    if escalation == Escalation::None {
+        return Ok(None);
+    }
+    eprintln!("Some operations need root, authenticating with {}...", escalation.name());
+    if escalation.caches_credentials() {
+        escalation.authenticate()?;
+        return Ok(escalation.keep_alive());
+    }
+    let session = Session::start(escalation)?;
+    if let Some(previous) = SESSION.lock().unwrap_or_else(PoisonError::into_inner).replace(session) {
+        previous.stop();
+    }
+    Ok(Some(KeepAlive::Session))
+}
+
+// Gets ready for privileged commands run one by one with the terminal in its normal mode. Tools that don't
+// reuse credentials ask again when each command runs, sudo is authenticated here.
+pub fn begin_command(preferred: Option<Escalation>) -> Result<(), String> {
+    let escalation = init(preferred)?;
+    if escalation.caches_credentials() {
+        escalation.authenticate()
+    } else {
+        Ok(())
+    }
+}
+
+// A command running program as root with the tool picked by init, falling back to sudo if none was
+pub fn command(program: &str) -> Command {
+    ESCALATION.get().copied().unwrap_or(Escalation::Sudo).command(program)
+}
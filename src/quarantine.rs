@@ -0,0 +1,414 @@
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder, File};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::filesystem::{dir_size, mount_points, path_error, xdg_dir};
+use crate::timestamp::{self, SECONDS_PER_DAY};
+
+pub const DEFAULT_RETENTION_DAYS: u64 = 7;
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+// Everything needed to put a quarantined path back the way it was
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ManifestEntry {
+    pub original_path: PathBuf,
+    pub stored_name: String, // Name under <run>/files/
+    pub owner: u32,
+    pub group: u32,
+    pub mode: u32,
+    pub mtime: i64,
+    pub size: u64,
+    #[serde(default)]
+    pub restored: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub created: u64,
+    pub operation: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+// One run's quarantine directory. A run that touched several filesystems has one of these per filesystem,
+// all sharing the same name.
+pub struct QuarantineRun {
+    pub name: String,
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl QuarantineRun {
+    pub fn pending_entries(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.manifest.entries.iter().filter(|entry| !entry.restored)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.pending_entries().map(|entry| entry.size).sum()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.manifest).map_err(|e| e.to_string())?;
+        fs::write(self.dir.join(MANIFEST_NAME), json).map_err(|e| format!("{}: {}", self.dir.display(), e))
+    }
+}
+
+// Moves paths into quarantine for a single run of an operation. Files stay on their own filesystem, so
+// quarantining is a rename and never a copy.
+pub struct QuarantineSession {
+    name: String,
+    operation: String,
+    runs: BTreeMap<PathBuf, QuarantineRun>, // Keyed by quarantine root
+}
+
+impl QuarantineSession {
+    pub fn new(operation: &str) -> QuarantineSession {
+        QuarantineSession {
+            name: timestamp::format(timestamp::now()),
+            operation: operation.to_string(),
+            runs: BTreeMap::new(),
+        }
+    }
+
+    // Quarantines a single path, returning its size
+    pub fn quarantine(&mut self, path: &Path) -> io::Result<u64> {
+        let metadata = fs::symlink_metadata(path).map_err(|e| path_error(path, e))?;
+        let original_path = absolute_path(path).map_err(|e| path_error(path, e))?;
+        let root = root_for(&original_path, metadata.dev())?;
+
+        if !self.runs.contains_key(&root) {
+            let run = self.create_run(&root)?;
+            self.runs.insert(root.clone(), run);
+        }
+        let run = self.runs.get_mut(&root).unwrap();
+
+        let stored_name = run.manifest.entries.len().to_string();
+        let size = dir_size(&original_path);
+        fs::rename(&original_path, run.dir.join("files").join(&stored_name)).map_err(|e| match e.raw_os_error() {
+            Some(libc::EXDEV) => io::Error::new(e.kind(), format!("{}: is on a different filesystem than {}", path.display(), root.display())),
+            _ => path_error(path, e),
+        })?;
+
+        run.manifest.entries.push(ManifestEntry {
+            original_path,
+            stored_name,
+            owner: metadata.uid(),
+            group: metadata.gid(),
+            mode: metadata.mode(),
+            mtime: metadata.mtime(),
+            size,
+            restored: false,
+        });
+        // Saved after every move, so an interrupted run can still be restored
+        run.save().map_err(io::Error::other)?;
+        Ok(size)
+    }
+
+    fn create_run(&self, root: &Path) -> io::Result<QuarantineRun> {
+        create_root(root)?;
+        let mut name = self.name.clone();
+        let mut suffix = 2;
+        while root.join(&name).exists() {
+            name = format!("{}-{}", self.name, suffix);
+            suffix += 1;
+        }
+        let dir = root.join(&name);
+        fs::create_dir_all(dir.join("files")).map_err(|e| path_error(&dir, e))?;
+
+        Ok(QuarantineRun {
+            name,
+            dir,
+            manifest: Manifest { created: timestamp::now(), operation: self.operation.clone(), entries: Vec::new() },
+        })
+    }
+}
+
+// Quarantines every path in a single run, returning the number of paths and bytes moved
+pub fn quarantine_paths(operation: &str, paths: &[PathBuf]) -> Result<(usize, u64), Vec<io::Error>> {
+    let mut session = QuarantineSession::new(operation);
+    let mut moved = 0;
+    let mut moved_bytes = 0;
+    let mut errors = Vec::new();
+    for path in paths {
+        match session.quarantine(path) {
+            Ok(size) => {
+                moved += 1;
+                moved_bytes += size;
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((moved, moved_bytes))
+    } else {
+        Err(errors)
+    }
+}
+
+// The quarantine in the user's data directory, used for everything on the same filesystem as it
+pub fn home_root() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share").map(|dir| dir.join("debloater").join("quarantine"))
+}
+
+// Quarantine root for other filesystems, at their top level like the trash spec's .Trash-$uid
+fn mount_root(mount: &Path) -> PathBuf {
+    mount.join(format!(".debloater-quarantine-{}", unsafe { libc::getuid() }))
+}
+
+// Quarantined files may be private, so the root is created for us alone. An existing one has to pass
+// check_root, so a directory or symlink someone else put there in advance is never used.
+fn create_root(root: &Path) -> io::Result<()> {
+    if let Some(parent) = root.parent() {
+        fs::create_dir_all(parent).map_err(|e| path_error(parent, e))?;
+    }
+    match DirBuilder::new().mode(0o700).create(root) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(path_error(root, e)),
+    }
+    check_root(root)
+}
+
+// Anyone can create directories at the top of a shared mount, so like the trash spec asks of .Trash-$uid, a root
+// has to be a real directory owned by us that no one else can get into
+fn check_root(root: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(root).map_err(|e| path_error(root, e))?;
+    let private = metadata.file_type().is_dir() && metadata.uid() == unsafe { libc::getuid() } && metadata.mode() & 0o777 == 0o700;
+    if private {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{}: is not a directory only you can access, refusing to use it as a quarantine", root.display()),
+        ))
+    }
+}
+
+fn root_for(path: &Path, device: u64) -> io::Result<PathBuf> {
+    let home = home_root().ok_or_else(|| io::Error::other("Could not determine the quarantine directory, is $HOME set?"))?;
+    if existing_device(&home) == Some(device) {
+        return Ok(home);
+    }
+
+    // Longest mount point containing the path that is on the same device
+    mount_points()
+        .into_iter()
+        .filter(|mount| path.starts_with(mount) && existing_device(mount) == Some(device))
+        .max_by_key(|mount| mount.components().count())
+        .map(|mount| mount_root(&mount))
+        .ok_or_else(|| io::Error::other(format!("{}: could not find the filesystem it is on", path.display())))
+}
+
+// Device of a path, or of its closest existing parent
+fn existing_device(path: &Path) -> Option<u64> {
+    path.ancestors().find_map(|ancestor| fs::metadata(ancestor).ok()).map(|metadata| metadata.dev())
+}
+
+fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+    // The parent is canonicalized rather than the path itself so that a symlink is quarantined, not its target
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::canonicalize(parent)?,
+        _ => std::env::current_dir()?,
+    };
+    match path.file_name() {
+        Some(name) => Ok(parent.join(name)),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot quarantine a filesystem root")),
+    }
+}
+
+// All quarantine runs on all mounted filesystems, oldest first
+pub fn list_runs() -> Vec<QuarantineRun> {
+    let mut roots: Vec<PathBuf> = home_root().into_iter().collect();
+    roots.extend(mount_points().iter().map(|mount| mount_root(mount)));
+    roots.sort();
+    roots.dedup();
+
+    let mut runs = Vec::new();
+    for root in roots {
+        // Manifests in a root we don't own could point a restore or purge anywhere
+        if check_root(&root).is_err() {
+            continue;
+        }
+        let entries = match fs::read_dir(&root) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            if let Some(manifest) = read_manifest(&dir) {
+                runs.push(QuarantineRun { name: entry.file_name().to_string_lossy().into_owned(), dir, manifest });
+            }
+        }
+    }
+    runs.sort_by(|a, b| a.manifest.created.cmp(&b.manifest.created).then_with(|| a.name.cmp(&b.name)));
+    runs
+}
+
+// The manifest of a run, if it has a readable one
+fn read_manifest(dir: &Path) -> Option<Manifest> {
+    fs::read_to_string(dir.join(MANIFEST_NAME)).ok().and_then(|json| serde_json::from_str(&json).ok())
+}
+
+// Restores the given entries of a run (all pending ones when None), returning how many were restored.
+// Existing files at the original location are never overwritten.
+pub fn restore(run: &mut QuarantineRun, original_paths: Option<&[PathBuf]>) -> Result<usize, String> {
+    let mut restored = 0;
+    let mut errors = Vec::new();
+    for entry in run.manifest.entries.iter_mut().filter(|entry| !entry.restored) {
+        if let Some(paths) = original_paths {
+            if !paths.contains(&entry.original_path) {
+                continue;
+            }
+        }
+        match restore_entry(&run.dir, entry) {
+            Ok(()) => {
+                entry.restored = true;
+                restored += 1;
+            }
+            Err(e) => errors.push(format!("{}: {}", entry.original_path.display(), e)),
+        }
+    }
+
+    if run.pending_entries().next().is_none() {
+        let _ = fs::remove_dir_all(&run.dir);
+    } else {
+        run.save()?;
+    }
+
+    if errors.is_empty() {
+        Ok(restored)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn restore_entry(run_dir: &Path, entry: &ManifestEntry) -> Result<(), String> {
+    let target = &entry.original_path;
+    if fs::symlink_metadata(target).is_ok() {
+        return Err(String::from("something already exists at this path"));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::rename(run_dir.join("files").join(&entry.stored_name), target).map_err(|e| e.to_string())?;
+
+    // A rename keeps all of this already, but put it back in case the file was touched while in quarantine.
+    // Ownership goes first since chown clears the setuid and setgid bits.
+    let metadata = fs::symlink_metadata(target).map_err(|e| e.to_string())?;
+    if metadata.uid() != entry.owner || metadata.gid() != entry.group {
+        // Only possible as root, otherwise the file simply stays owned by us
+        let _ = std::os::unix::fs::lchown(target, Some(entry.owner), Some(entry.group));
+    }
+    if !metadata.file_type().is_symlink() {
+        let _ = fs::set_permissions(target, fs::Permissions::from_mode(entry.mode & 0o7777));
+        let mtime = UNIX_EPOCH.checked_add(Duration::from_secs(entry.mtime.max(0) as u64));
+        if let (Ok(file), Some(mtime)) = (File::open(target), mtime) {
+            let _ = file.set_modified(mtime);
+        }
+    }
+    Ok(())
+}
+
+// Permanently deletes runs older than the retention period, returning the number of runs and bytes removed
+pub fn purge_expired(retention_days: u64) -> Result<(usize, u64), String> {
+    let now = timestamp::now();
+    let mut purged = 0;
+    let mut purged_bytes = 0;
+    let mut errors = Vec::new();
+    for run in list_runs() {
+        if now.saturating_sub(run.manifest.created) < retention_days.saturating_mul(SECONDS_PER_DAY) {
+            continue;
+        }
+        let size = run.size();
+        match fs::remove_dir_all(&run.dir) {
+            Ok(()) => {
+                purged += 1;
+                purged_bytes += size;
+            }
+            Err(e) => errors.push(format!("{}: {}", run.dir.display(), e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((purged, purged_bytes))
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty directory of its own for each test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("debloater-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry(original_path: &str, restored: bool) -> ManifestEntry {
+        ManifestEntry {
+            original_path: PathBuf::from(original_path),
+            stored_name: String::from("0-file"),
+            owner: 1000,
+            group: 1000,
+            mode: 0o100644,
+            mtime: -1,
+            size: u64::MAX,
+            restored,
+        }
+    }
+
+    #[test]
+    fn manifests_round_trip() {
+        let dir = test_dir("manifest");
+        let entries = vec![entry("/home/user/.cache/a b", false), entry("/home/user/.cache/\u{e9}", true)];
+        let run = QuarantineRun {
+            name: String::from("run"),
+            dir: dir.clone(),
+            manifest: Manifest { created: 1709210096, operation: String::from("clean-user-cache"), entries },
+        };
+        run.save().unwrap();
+        let manifest = read_manifest(&dir).unwrap();
+        assert_eq!(serde_json::to_value(&manifest).unwrap(), serde_json::to_value(&run.manifest).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn manifests_without_restored_are_pending() {
+        let dir = test_dir("old-manifest");
+        let json = r#"{"created": 1, "operation": "clean-user-cache", "entries": [
+            {"original_path": "/a", "stored_name": "0-a", "owner": 0, "group": 0, "mode": 420, "mtime": 0, "size": 1}
+        ]}"#;
+        fs::write(dir.join(MANIFEST_NAME), json).unwrap();
+        let manifest = read_manifest(&dir).unwrap();
+        assert!(!manifest.entries[0].restored);
+        fs::write(dir.join(MANIFEST_NAME), "{\"created\": 1}").unwrap();
+        assert!(read_manifest(&dir).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn roots_are_private_directories() {
+        let dir = test_dir("root");
+        let root = dir.join("quarantine");
+        create_root(&root).unwrap();
+        assert_eq!(fs::metadata(&root).unwrap().mode() & 0o777, 0o700);
+        // Creating it again finds it as it was
+        create_root(&root).unwrap();
+
+        fs::set_permissions(&root, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check_root(&root).is_err());
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+        fs::set_permissions(&root, fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(check_root(&link).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+use serde::Serialize;
+use crate::execution::{OperationError, OperationResult, Outcome};
+use crate::history::SnapshotRecord;
+use crate::system;
+
+// Bumped whenever a field is removed or changes meaning. Adding fields does not change it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct ErrorReport {
+    pub kind: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl From<&OperationError> for ErrorReport {
+    fn from(error: &OperationError) -> ErrorReport {
+        ErrorReport {
+            kind: error.kind(),
+            message: error.to_string(),
+            exit_code: match error {
+                OperationError::NonZeroExit { code, .. } => *code,
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PlannedOperation {
+    pub index: usize,
+    pub id: &'static str,
+    pub name: &'static str,
+    pub estimated_bytes: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct OperationReport {
+    pub index: usize,
+    pub id: &'static str,
+    pub name: &'static str,
+    pub outcome: &'static str, // "succeeded", "failed" or "skipped"
+    pub error: Option<ErrorReport>,
+    pub estimated_bytes: Option<u64>,
+    pub bytes_freed: u64,
+    pub items_removed: usize,
+    pub duration_ms: u64,
+    pub commands: Vec<String>,
+    pub stderr: String,
+    pub warnings: Vec<String>,
+}
+
+impl OperationReport {
+    pub fn new(planned: &PlannedOperation, result: &OperationResult) -> OperationReport {
+        let (outcome, error) = match &result.outcome {
+            Outcome::Succeeded => ("succeeded", None),
+            Outcome::Failed(e) => ("failed", Some(ErrorReport::from(e))),
+            Outcome::Skipped(e) => ("skipped", Some(ErrorReport::from(e))),
+        };
+        OperationReport {
+            index: planned.index,
+            id: planned.id,
+            name: planned.name,
+            outcome,
+            error,
+            estimated_bytes: planned.estimated_bytes,
+            bytes_freed: result.bytes_freed,
+            items_removed: result.items_removed,
+            duration_ms: result.duration.as_millis() as u64,
+            commands: result.commands.clone(),
+            stderr: result.stderr.clone(),
+            warnings: result.warnings.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Totals {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub estimated_bytes: u64,
+    pub bytes_freed: u64,
+}
+
+impl Totals {
+    pub fn new(reports: &[OperationReport]) -> Totals {
+        let count = |outcome: &str| reports.iter().filter(|report| report.outcome == outcome).count();
+        Totals {
+            succeeded: count("succeeded"),
+            failed: count("failed"),
+            skipped: count("skipped"),
+            estimated_bytes: reports.iter().filter_map(|report| report.estimated_bytes).sum(),
+            bytes_freed: reports.iter().map(|report| report.bytes_freed).sum(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Host {
+    pub hostname: String,
+    pub distro: String,
+}
+
+impl Host {
+    pub fn current() -> Host {
+        Host {
+            hostname: system::hostname(),
+            distro: system::distribution_id().unwrap_or_else(|| String::from("unknown")),
+        }
+    }
+}
+
+// A line of `--output ndjson`
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    RunStarted {
+        schema_version: u32,
+        timestamp: u64,
+        #[serde(flatten)]
+        host: &'a Host,
+        operations: &'a [PlannedOperation],
+    },
+    OperationStarted {
+        index: usize,
+        id: &'static str,
+    },
+    Output {
+        index: Option<usize>, // None for output that belongs to no operation, eg. snapshots
+        line: &'a str,
+    },
+    OperationFinished(&'a OperationReport),
+    Snapshot(&'a SnapshotRecord),
+    RunFinished(&'a Totals),
+}
+
+// The single document of `--output json`
+#[derive(Serialize)]
+pub struct RunReport<'a> {
+    pub schema_version: u32,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub host: &'a Host,
+    pub operations: &'a [OperationReport],
+    pub totals: &'a Totals,
+    pub snapshot: Option<&'a SnapshotRecord>, // Taken before the package operations
+}
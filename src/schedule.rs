@@ -0,0 +1,410 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::config;
+use crate::filesystem::{find_in_path, xdg_dir};
+use crate::privilege;
+
+const SYSTEM_UNIT_DIR: &str = "/etc/systemd/system";
+
+// The frequencies offered by the TUI, any OnCalendar expression works from the command line
+pub const FREQUENCIES: [&str; 3] = ["daily", "weekly", "monthly"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    System, // Runs as root
+    User,   // Runs as the user installing it, while they are logged in
+}
+
+impl Scope {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+        }
+    }
+
+    fn unit_dir(&self) -> Option<PathBuf> {
+        match self {
+            Self::System => Some(PathBuf::from(SYSTEM_UNIT_DIR)),
+            Self::User => xdg_dir("XDG_CONFIG_HOME", ".config").map(|config| config.join("systemd").join("user")),
+        }
+    }
+
+    // systemctl for this scope. System units are changed as root, but anyone can query them.
+    fn systemctl(&self, as_root: bool) -> Command {
+        match self {
+            Self::System if as_root => privilege::command("systemctl"),
+            Self::System => Command::new("systemctl"),
+            Self::User => {
+                let mut command = Command::new("systemctl");
+                command.arg("--user");
+                command
+            }
+        }
+    }
+}
+
+// What a schedule runs. Units are named after the kind and the name, eg. debloater-profile-weekly.timer, so that a
+// profile named like a category can't replace the category's timer.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Profile,  // Installed with `debloater install-schedule`
+    Category, // Set up on the schedule screen of the TUI
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Profile => "profile",
+            Self::Category => "category",
+        }
+    }
+
+    fn unit_prefix(&self) -> &'static str {
+        match self {
+            Self::Profile => "debloater-profile-",
+            Self::Category => "debloater-category-",
+        }
+    }
+
+    fn unit(&self, name: &str) -> String {
+        format!("{}{}", self.unit_prefix(), name)
+    }
+}
+
+pub struct Schedule {
+    pub kind: Kind,
+    pub name: String,
+    pub scope: Scope,
+    pub frequency: String, // An OnCalendar expression, eg. "weekly"
+    pub args: Vec<String>, // What debloater is run with, eg. ["profile", "run", "weekly"]
+}
+
+// An installed schedule, with what systemd knows about it
+pub struct Installed {
+    pub schedule: Schedule,
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    pub last_result: Option<String>, // "success", or how the last run failed
+}
+
+impl Schedule {
+    fn unit(&self) -> String {
+        self.kind.unit(&self.name)
+    }
+
+    fn service(&self) -> Result<String, String> {
+        let exe = std::env::current_exe().map_err(|e| format!("Could not find the debloater executable: {}", e))?;
+        let command: Vec<String> = [exe.display().to_string()].into_iter().chain(self.args.iter().cloned()).map(|arg| quote(&arg)).collect();
+        Ok(format!(
+            "[Unit]\n\
+             Description=debloater scheduled cleanup ({})\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             ExecStart={}\n\
+             Environment=NO_COLOR=1\n\
+             Nice=19\n\
+             IOSchedulingClass=idle\n",
+            self.name,
+            command.join(" ")
+        ))
+    }
+
+    fn timer(&self) -> String {
+        format!(
+            "[Unit]\n\
+             Description=Run {}.service {}\n\
+             \n\
+             [Timer]\n\
+             OnCalendar={}\n\
+             Persistent=true\n\
+             RandomizedDelaySec=1h\n\
+             \n\
+             [Install]\n\
+             WantedBy=timers.target\n",
+            self.unit(),
+            self.frequency,
+            self.frequency
+        )
+    }
+}
+
+// systemd splits ExecStart= on whitespace unless the argument is quoted, and expands % specifiers and $ variables
+// unless they are doubled
+fn quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%").replace('$', "$$");
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\')) {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        arg
+    }
+}
+
+// Splits an ExecStart= command line into its words the way systemd does: on whitespace outside of double or single
+// quotes, with C-style backslash escapes and doubled % and $. None if the quoting is broken.
+fn split_command(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut chars = command.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else { break };
+        let quote = chars.next_if(|_| first == '"' || first == '\'');
+        let mut word = String::new();
+        loop {
+            match chars.next() {
+                None if quote.is_some() => return None,
+                None => break,
+                // A closing quote has to end the word
+                Some(c) if Some(c) == quote => match chars.peek() {
+                    Some(next) if !next.is_whitespace() => return None,
+                    _ => break,
+                },
+                Some(c) if quote.is_none() && c.is_whitespace() => break,
+                Some('\\') => word.push(unescape(&mut chars)?),
+                Some(c @ ('%' | '$')) => {
+                    chars.next_if_eq(&c);
+                    word.push(c);
+                }
+                Some(c) => word.push(c),
+            }
+        }
+        words.push(word);
+    }
+    Some(words)
+}
+
+// The character a backslash escape in a unit file stands for, the backslash already read
+fn unescape(chars: &mut impl Iterator<Item = char>) -> Option<char> {
+    match chars.next()? {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        's' => Some(' '),
+        'x' => {
+            let digits: String = chars.take(2).collect();
+            u8::from_str_radix(&digits, 16).ok().filter(u8::is_ascii).map(char::from)
+        }
+        c @ ('\\' | '"' | '\'') => Some(c),
+        _ => None,
+    }
+}
+
+pub fn is_schedule_name(name: &str) -> bool {
+    config::is_profile_name(name)
+}
+
+// Checks an OnCalendar expression with systemd-analyze, when it is installed
+pub fn validate_frequency(frequency: &str) -> Result<(), String> {
+    if frequency.trim().is_empty() {
+        return Err(String::from("The frequency can't be empty"));
+    }
+    if find_in_path("systemd-analyze").is_none() {
+        return Ok(());
+    }
+    let output = Command::new("systemd-analyze")
+        .args(["calendar", frequency])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("systemd-analyze: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("\"{}\" is not a valid systemd calendar expression, eg. daily, weekly or Sun 03:00", frequency))
+    }
+}
+
+fn run(command: &mut Command) -> Result<String, String> {
+    let output = command.stdin(Stdio::null()).output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+// Writes a unit file, through a root command for system units. The contents go through a pipe rather than a
+// temporary file, which another user could swap before root copies it.
+fn write_unit(scope: Scope, path: &Path, contents: &str) -> Result<(), String> {
+    match scope {
+        Scope::User => fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e)),
+        Scope::System => {
+            let mut child = privilege::command("tee")
+                .arg(path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            // Dropping stdin once written closes the pipe, so tee finishes
+            let written = child.stdin.take().map(|mut stdin| stdin.write_all(contents.as_bytes()));
+            let output = child.wait_with_output().map_err(|e| format!("{}: {}", path.display(), e))?;
+            if !output.status.success() {
+                return Err(format!("{}: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            match written {
+                Some(Ok(())) => Ok(()),
+                Some(Err(e)) => Err(format!("{}: {}", path.display(), e)),
+                None => Err(format!("{}: could not write to tee", path.display())),
+            }
+        }
+    }
+}
+
+// Writes the service and timer, then enables the timer. Installing over an existing schedule replaces it.
+// System schedules ask for a password, so the terminal has to be in its normal mode.
+pub fn install(schedule: &Schedule) -> Result<(), String> {
+    if !is_schedule_name(&schedule.name) {
+        return Err(String::from("Schedule names can only contain letters, digits, - and _"));
+    }
+    validate_frequency(&schedule.frequency)?;
+    let dir = schedule.scope.unit_dir().ok_or_else(|| String::from("Could not find the config directory, is $HOME set?"))?;
+    if schedule.scope == Scope::System {
+        privilege::begin_command(config::get().escalation)?;
+    } else {
+        fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+
+    let unit = schedule.unit();
+    write_unit(schedule.scope, &dir.join(format!("{}.service", unit)), &schedule.service()?)?;
+    write_unit(schedule.scope, &dir.join(format!("{}.timer", unit)), &schedule.timer())?;
+    run(schedule.scope.systemctl(true).arg("daemon-reload"))
+        .and_then(|_| run(schedule.scope.systemctl(true).args(["enable", "--now"]).arg(format!("{}.timer", unit))))
+        .map(|_| ())
+        .map_err(|e| format!("Wrote the units to {}, but could not enable {}.timer: {}", dir.display(), unit, e))
+}
+
+// Stops and removes a schedule. Returns false if it wasn't installed.
+pub fn uninstall(kind: Kind, name: &str, scope: Scope) -> Result<bool, String> {
+    let dir = scope.unit_dir().ok_or_else(|| String::from("Could not find the config directory, is $HOME set?"))?;
+    let unit = kind.unit(name);
+    let files = [dir.join(format!("{}.timer", unit)), dir.join(format!("{}.service", unit))];
+    if !files.iter().any(|file| file.exists()) {
+        return Ok(false);
+    }
+
+    if scope == Scope::System {
+        privilege::begin_command(config::get().escalation)?;
+    }
+    // Disabling fails if systemd never loaded the timer, which doesn't stop it from being removed
+    let _ = run(scope.systemctl(true).args(["disable", "--now"]).arg(format!("{}.timer", unit)));
+    for file in files.iter().filter(|file| file.exists()) {
+        match scope {
+            Scope::User => fs::remove_file(file).map_err(|e| format!("{}: {}", file.display(), e))?,
+            Scope::System => {
+                run(privilege::command("rm").arg("-f").arg(file)).map_err(|e| format!("{}: {}", file.display(), e))?;
+            }
+        }
+    }
+    // The files are gone either way, systemd notices on its next reload
+    let _ = run(scope.systemctl(true).arg("daemon-reload"));
+    Ok(true)
+}
+
+// Every installed schedule, system ones first, and profiles before categories
+pub fn installed() -> Vec<Installed> {
+    let mut schedules = Vec::new();
+    for scope in [Scope::System, Scope::User] {
+        let Some(dir) = scope.unit_dir() else { continue };
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        let files: Vec<String> = entries.flatten().filter_map(|entry| entry.file_name().into_string().ok()).collect();
+        for kind in [Kind::Profile, Kind::Category] {
+            let mut names: Vec<&str> = files
+                .iter()
+                .filter_map(|file| file.strip_prefix(kind.unit_prefix())?.strip_suffix(".timer"))
+                .filter(|name| is_schedule_name(name))
+                .collect();
+            names.sort();
+            for name in names {
+                let name = name.to_string();
+                let unit = kind.unit(&name);
+                let timer = fs::read_to_string(dir.join(format!("{}.timer", unit))).unwrap_or_default();
+                let service = fs::read_to_string(dir.join(format!("{}.service", unit))).unwrap_or_default();
+                let frequency = unit_value(&timer, "OnCalendar").unwrap_or_default();
+                let args = exec_args(&service);
+
+                let timer_state = systemd_properties(scope, &format!("{}.timer", unit), &["NextElapseUSecRealtime", "LastTriggerUSec"]);
+                let service_state = systemd_properties(scope, &format!("{}.service", unit), &["Result"]);
+                schedules.push(Installed {
+                    schedule: Schedule { kind, name, scope, frequency, args },
+                    next_run: timer_state[0].clone(),
+                    last_run: timer_state[1].clone(),
+                    // Result is "success" even for a service that never ran
+                    last_result: service_state[0].clone().filter(|_| timer_state[1].is_some()),
+                });
+            }
+        }
+    }
+    schedules
+}
+
+// What a service runs debloater with: everything after the executable in its ExecStart=
+fn exec_args(service: &str) -> Vec<String> {
+    unit_value(service, "ExecStart")
+        .and_then(|command| split_command(&command))
+        .map(|words| words.into_iter().skip(1).collect())
+        .unwrap_or_default()
+}
+
+fn unit_value(unit: &str, key: &str) -> Option<String> {
+    unit.lines().find_map(|line| line.strip_prefix(key)?.strip_prefix('=')).map(|value| value.trim().to_string())
+}
+
+// The given properties of a unit, None for those that are unset or if systemd can't be asked
+fn systemd_properties(scope: Scope, unit: &str, properties: &[&str]) -> Vec<Option<String>> {
+    let mut command = scope.systemctl(false);
+    command.args(["show", unit]);
+    for property in properties {
+        command.arg(format!("--property={}", property));
+    }
+    let output = run(&mut command).unwrap_or_default();
+    properties
+        .iter()
+        .map(|property| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix(property)?.strip_prefix('='))
+                .map(str::trim)
+                .filter(|value| !value.is_empty() && *value != "n/a" && *value != "0")
+                .map(String::from)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_back_what_service_writes() {
+        let args = words(&["profile", "run", "my profile", "say \"hi\"", "back\\slash", "100%", "$HOME", "it's", ""]);
+        let schedule = Schedule { kind: Kind::Profile, name: String::from("test"), scope: Scope::User, frequency: String::from("weekly"), args: args.clone() };
+        assert_eq!(exec_args(&schedule.service().unwrap()), args);
+    }
+
+    #[test]
+    fn splits_systemd_quoting() {
+        let split = |command| split_command(command).unwrap();
+        assert_eq!(split("/usr/bin/debloater profile run weekly"), words(&["/usr/bin/debloater", "profile", "run", "weekly"]));
+        assert_eq!(split("  /bin/a   'b c'  \"d e\" "), words(&["/bin/a", "b c", "d e"]));
+        assert_eq!(split("/bin/a \"say \\\"hi\\\"\" 'it\\'s'"), words(&["/bin/a", "say \"hi\"", "it's"]));
+        assert_eq!(split("/bin/a b\\sc \\x41 \\\\"), words(&["/bin/a", "b c", "A", "\\"]));
+        assert_eq!(split("/bin/a 100%% $$HOME"), words(&["/bin/a", "100%", "$HOME"]));
+        assert_eq!(split("/bin/a \"\""), words(&["/bin/a", ""]));
+    }
+
+    #[test]
+    fn rejects_broken_quoting() {
+        for command in ["/bin/a \"unclosed", "/bin/a 'unclosed", "/bin/a \"b\"c", "/bin/a \\q", "/bin/a trailing\\", "/bin/a \\xZZ"] {
+            assert_eq!(split_command(command), None, "{}", command);
+        }
+        // Broken quoting leaves the arguments empty rather than guessing
+        assert!(exec_args("[Service]\nExecStart=/bin/a \"b\n").is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use crate::filesystem::find_in_path;
+use crate::privilege;
+
+pub enum SnapshotTool {
+    Snapper,
+    Timeshift,
+}
+
+pub struct Snapshot {
+    pub tool: SnapshotTool,
+    pub id: String, // Snapper's snapshot number, or timeshift's snapshot name
+}
+
+impl SnapshotTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Snapper => "snapper",
+            Self::Timeshift => "timeshift",
+        }
+    }
+}
+
+impl Snapshot {
+    // Command that undoes everything since the snapshot was taken, to be run as root
+    pub fn rollback_hint(&self) -> String {
+        match self.tool {
+            SnapshotTool::Snapper => format!("snapper -c root undochange {}..0", self.id),
+            SnapshotTool::Timeshift => format!("timeshift --restore --snapshot '{}'", self.id),
+        }
+    }
+
+    // Snapper pairs pre snapshots with a post snapshot, so `snapper status` can show what the operations changed.
+    // Returns the number of the post snapshot, if one was taken.
+    pub fn finish(&self, description: &str) -> Result<Option<String>, String> {
+        match self.tool {
+            SnapshotTool::Snapper => {
+                let id = run(privilege::command("snapper").args([
+                    "-c", "root", "create", "--type", "post", "--pre-number", &self.id, "--print-number",
+                    "--cleanup-algorithm", "number", "--description", description,
+                ]))?;
+                Ok(Some(id.trim().to_string()).filter(|id| !id.is_empty()))
+            }
+            SnapshotTool::Timeshift => Ok(None),
+        }
+    }
+}
+
+// Snapshots are only taken on a Btrfs root, with snapper (configured for root) or timeshift installed
+pub fn detect() -> Option<SnapshotTool> {
+    if root_filesystem_type().as_deref() != Some("btrfs") {
+        return None;
+    }
+    if find_in_path("snapper").is_some() && Path::new("/etc/snapper/configs/root").exists() {
+        Some(SnapshotTool::Snapper)
+    } else if find_in_path("timeshift").is_some() {
+        Some(SnapshotTool::Timeshift)
+    } else {
+        None
+    }
+}
+
+fn root_filesystem_type() -> Option<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    // Later mounts shadow earlier ones, so the last entry for / is the one in use
+    mounts
+        .lines()
+        .rev()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_, mount, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+            (mount == "/").then(|| fs_type.to_string())
+        })
+        .next()
+}
+
+pub fn create_pre_snapshot(tool: SnapshotTool, description: &str) -> Result<Snapshot, String> {
+    let id = match tool {
+        SnapshotTool::Snapper => run(privilege::command("snapper").args([
+            "-c", "root", "create", "--type", "pre", "--print-number",
+            "--cleanup-algorithm", "number", "--description", description,
+        ]))?
+        .trim()
+        .to_string(),
+        SnapshotTool::Timeshift => {
+            let output = run(privilege::command("timeshift").args(["--create", "--scripted", "--comments", description]))?;
+            // eg. "Tagged snapshot '2024-05-01_10-00-00': ondemand"
+            output
+                .lines()
+                .find_map(|line| line.split_once("snapshot '").and_then(|(_, rest)| rest.split_once('\'')).map(|(name, _)| name.to_string()))
+                .ok_or_else(|| String::from("timeshift did not report the name of the new snapshot"))?
+        }
+    };
+    if id.is_empty() {
+        return Err(format!("{} did not report the new snapshot", tool.name()));
+    }
+    Ok(Snapshot { tool, id })
+}
+
+fn run(command: &mut Command) -> Result<String, String> {
+    let (mut stdout, mut stderr) = (String::new(), String::new());
+    let session = privilege::run_in_session(command, &mut |line, from_stderr| {
+        let output = if from_stderr { &mut stderr } else { &mut stdout };
+        output.push_str(line);
+        output.push('\n');
+    });
+    match session {
+        Some(Ok(Some(0))) => return Ok(stdout),
+        Some(Ok(_)) => return Err(stderr),
+        Some(Err(e)) => return Err(e.to_string()),
+        None => {}
+    }
+    let output = command.output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
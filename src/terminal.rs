@@ -0,0 +1,66 @@
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io;
+use std::panic;
+use std::sync::Once;
+use std::thread;
+use tui::{backend::Backend, Terminal};
+
+static PANIC_HOOK: Once = Once::new();
+
+// Keeps the terminal in raw mode on the alternate screen, with mouse capture, for as long as it lives. The
+// terminal is put back when it is dropped, so also on errors returned early with `?`, and when the thread that
+// created it panics, so the panic message ends up on a usable screen.
+pub struct TerminalGuard(());
+
+impl TerminalGuard {
+    pub fn enter() -> io::Result<TerminalGuard> {
+        install_panic_hook();
+        enter()?;
+        Ok(TerminalGuard(()))
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = leave();
+    }
+}
+
+// Hands the terminal back for the duration of f, eg. for a password prompt, then takes it over again and has
+// everything redrawn
+pub fn suspend<B: Backend, T>(terminal: &mut Terminal<B>, f: impl FnOnce() -> T) -> io::Result<T> {
+    leave()?;
+    let result = f();
+    enter()?;
+    terminal.clear()?;
+    Ok(result)
+}
+
+fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+fn leave() -> io::Result<()> {
+    execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)?;
+    disable_raw_mode()
+}
+
+// Background threads like scans can panic without taking the TUI down, so only a panic on the TUI's own
+// thread restores the terminal
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let tui_thread = thread::current().id();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == tui_thread {
+                let _ = leave();
+            }
+            previous(info);
+        }));
+    });
+}
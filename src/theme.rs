@@ -0,0 +1,116 @@
+use std::sync::OnceLock;
+use tui::style::{Color, Modifier, Style};
+use crate::config;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ThemeName {
+    Dark,
+    Light,
+    HighContrast,
+    Monochrome,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [Self::Dark, Self::Light, Self::HighContrast, Self::Monochrome];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::HighContrast => "high-contrast",
+            Self::Monochrome => "monochrome",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ThemeName> {
+        Self::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+}
+
+// Styles by what they mean rather than how they look. Every status shown in a colour is also told apart by
+// text, like [x], [fail] or a ! marker, so nothing is lost with the monochrome theme.
+pub struct Theme {
+    pub colors: bool,     // Whether colours are used at all, eg. for the dashboard's gauges
+    pub heading: Style,   // Category headers and list headers
+    pub highlight: Style, // The highlighted row of a list
+    pub key: Style,       // Keys in the hints at the bottom
+    pub input: Style,     // Text being typed
+    pub ok: Style,
+    pub warning: Style,
+    pub error: Style,
+    pub notice: Style,    // Neither good nor bad, eg. skipped operations or quarantined files
+    pub dimmed: Style,    // Things that can't be used
+    pub track: Color,     // Unfilled part of a gauge
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+// The theme from the config, or the monochrome one if $NO_COLOR is set, see https://no-color.org
+pub fn get() -> &'static Theme {
+    THEME.get_or_init(|| {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Theme::new(if no_color { ThemeName::Monochrome } else { config::get().theme })
+    })
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Theme {
+        let fg = |color| Style::default().fg(color);
+        let bold = |color| Style::default().fg(color).add_modifier(Modifier::BOLD);
+        match name {
+            ThemeName::Dark => Theme {
+                colors: true,
+                heading: bold(Color::Cyan),
+                highlight: bold(Color::Yellow),
+                key: bold(Color::Cyan),
+                input: fg(Color::Yellow),
+                ok: fg(Color::Green),
+                warning: fg(Color::Yellow),
+                error: fg(Color::Red),
+                notice: fg(Color::Magenta),
+                dimmed: fg(Color::DarkGray),
+                track: Color::DarkGray,
+            },
+            // The bright colours of the dark theme are hard to read on a white background
+            ThemeName::Light => Theme {
+                colors: true,
+                heading: bold(Color::Blue),
+                highlight: bold(Color::Magenta),
+                key: bold(Color::Blue),
+                input: fg(Color::Blue),
+                ok: fg(Color::Green),
+                warning: fg(Color::Indexed(130)),
+                error: fg(Color::Red),
+                notice: fg(Color::Magenta),
+                dimmed: fg(Color::Indexed(245)),
+                track: Color::Indexed(252),
+            },
+            ThemeName::HighContrast => Theme {
+                colors: true,
+                heading: bold(Color::White).add_modifier(Modifier::UNDERLINED),
+                highlight: Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD),
+                key: bold(Color::LightCyan),
+                input: bold(Color::LightYellow),
+                ok: bold(Color::LightGreen),
+                warning: bold(Color::LightYellow),
+                error: bold(Color::LightRed),
+                notice: bold(Color::LightMagenta),
+                dimmed: fg(Color::Gray),
+                track: Color::Gray,
+            },
+            ThemeName::Monochrome => Theme {
+                colors: false,
+                heading: Style::default().add_modifier(Modifier::BOLD),
+                highlight: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                key: Style::default().add_modifier(Modifier::BOLD),
+                input: Style::default().add_modifier(Modifier::UNDERLINED),
+                ok: Style::default(),
+                warning: Style::default().add_modifier(Modifier::BOLD),
+                error: Style::default().add_modifier(Modifier::BOLD),
+                notice: Style::default(),
+                dimmed: Style::default().add_modifier(Modifier::DIM),
+                track: Color::Reset,
+            },
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use crate::filesystem::{path_error, xdg_dir};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// A URI is far shorter than this, bigger text chunks are skipped instead of read into memory
+const MAX_TEXT_CHUNK: usize = 64 * 1024;
+
+// Thumbnail directories per the freedesktop.org thumbnail spec, including the per-application failure caches
+pub fn thumbnail_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(root) = xdg_dir("XDG_CACHE_HOME", ".cache").map(|dir| dir.join("thumbnails")) {
+        for size in ["normal", "large", "x-large", "xx-large"] {
+            dirs.push(root.join(size));
+        }
+        if let Ok(entries) = fs::read_dir(root.join("fail")) {
+            dirs.extend(entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()));
+        }
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+// Thumbnails whose source file no longer exists, with their sizes. Thumbnails of remote URIs, and ones
+// without a readable Thumb::URI, are left alone.
+pub fn find_stale() -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut stale = Vec::new();
+    for dir in thumbnail_dirs() {
+        let entries = fs::read_dir(&dir).map_err(|e| path_error(&dir, e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().map(|ext| ext != "png").unwrap_or(true) {
+                continue;
+            }
+            let source = match read_thumb_uri(&path).ok().flatten().and_then(|uri| file_uri_to_path(&uri)) {
+                Some(source) => source,
+                None => continue,
+            };
+            // Only a source that is definitely gone makes a thumbnail stale, not one that can't be checked
+            let missing = matches!(fs::symlink_metadata(&source), Err(e) if e.kind() == io::ErrorKind::NotFound);
+            if !missing || is_on_removable_media(&source) {
+                continue;
+            }
+            stale.push((path, entry.metadata().map(|m| m.len()).unwrap_or(0)));
+        }
+    }
+    Ok(stale)
+}
+
+// Removes stale thumbnails, except those keep picks, returning the number of thumbnails and bytes removed
+pub fn remove_stale(keep: impl Fn(&Path) -> bool) -> Result<(usize, u64), Vec<io::Error>> {
+    let mut removed = 0;
+    let mut removed_bytes = 0;
+    let mut errors = Vec::new();
+    for (path, size) in find_stale().map_err(|e| vec![e])?.into_iter().filter(|(path, _)| !keep(path)) {
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                removed += 1;
+                removed_bytes += size;
+            }
+            Err(e) => errors.push(path_error(&path, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((removed, removed_bytes))
+    } else {
+        Err(errors)
+    }
+}
+
+// Files on removable drives look missing whenever the drive is unplugged, so their thumbnails are kept
+fn is_on_removable_media(path: &Path) -> bool {
+    ["/media", "/run/media", "/mnt"].iter().any(|root| path.starts_with(root))
+}
+
+// Reads the Thumb::URI text chunk of a PNG thumbnail. Only the chunks before the image data are read,
+// which is where thumbnailers put their metadata.
+pub fn read_thumb_uri(path: &Path) -> io::Result<Option<String>> {
+    let mut file = File::open(path)?;
+    let mut signature = [0u8; 8];
+    file.read_exact(&mut signature)?;
+    if signature != PNG_SIGNATURE {
+        return Ok(None);
+    }
+
+    loop {
+        let mut header = [0u8; 8];
+        if file.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = &header[4..8];
+
+        match chunk_type {
+            b"IDAT" | b"IEND" => return Ok(None),
+            b"tEXt" | b"iTXt" if length <= MAX_TEXT_CHUNK => {
+                let mut data = vec![0u8; length];
+                file.read_exact(&mut data)?;
+                if let Some(uri) = parse_text_chunk(chunk_type, &data) {
+                    return Ok(Some(uri));
+                }
+            }
+            _ => {
+                io::copy(&mut (&mut file).take(length as u64), &mut io::sink())?;
+            }
+        }
+        // Skip the CRC
+        let mut crc = [0u8; 4];
+        file.read_exact(&mut crc)?;
+    }
+}
+
+fn parse_text_chunk(chunk_type: &[u8], data: &[u8]) -> Option<String> {
+    let keyword_end = data.iter().position(|&b| b == 0)?;
+    if &data[..keyword_end] != b"Thumb::URI" {
+        return None;
+    }
+    let rest = &data[keyword_end + 1..];
+    let text = if chunk_type == b"iTXt" {
+        // Compression flag, compression method, then null terminated language tag and translated keyword
+        let (compressed, rest) = (*rest.first()?, rest.get(2..)?);
+        if compressed != 0 {
+            return None;
+        }
+        let language_end = rest.iter().position(|&b| b == 0)?;
+        let rest = &rest[language_end + 1..];
+        let translated_end = rest.iter().position(|&b| b == 0)?;
+        &rest[translated_end + 1..]
+    } else {
+        rest
+    };
+    Some(String::from_utf8_lossy(text).into_owned())
+}
+
+// Converts a file:// URI to a local path, decoding percent escapes. Any other scheme returns None.
+fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // Skip an optional host component, eg. file://localhost/home/...
+    let path = &path[path.find('/')?..];
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = path.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    Some(PathBuf::from(OsString::from_vec(decoded)))
+}
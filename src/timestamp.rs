@@ -0,0 +1,100 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Seconds since the epoch
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Parses a YYYY-MM-DDThh:mm:ss timestamp, ignoring anything after the seconds (fractions, timezones)
+pub fn parse(timestamp: &str) -> Option<u64> {
+    let (day, time) = timestamp.split_once('T')?;
+    let mut day_parts = day.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (day_parts.next()??, day_parts.next()??, day_parts.next()??);
+    let mut time_parts = time.splitn(3, ':').map(|part| part.get(..2).and_then(|p| p.parse::<i64>().ok()));
+    let (hour, minute, second) = (time_parts.next()??, time_parts.next()??, time_parts.next()??);
+    // Four digit years keep the arithmetic below far from overflowing. 60 seconds is a leap second.
+    let in_range = (0..=9999).contains(&year)
+        && (1..=12).contains(&month)
+        && (1..=31).contains(&day)
+        && (0..24).contains(&hour)
+        && (0..60).contains(&minute)
+        && (0..=60).contains(&second);
+    if !in_range {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY as i64 + hour * 3600 + minute * 60 + second;
+    u64::try_from(seconds).ok()
+}
+
+// Formats seconds since the epoch as YYYY-MM-DDThh:mm:ss in UTC
+pub fn format(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+    let time = seconds % SECONDS_PER_DAY;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+// Rough age of a timestamp for display, eg. "3 days ago"
+pub fn format_age(seconds: u64) -> String {
+    let age = now().saturating_sub(seconds);
+    match age {
+        0..=3599 => format!("{} minutes ago", age / 60),
+        3600..=86399 => format!("{} hours ago", age / 3600),
+        _ => format!("{} days ago", age / SECONDS_PER_DAY),
+    }
+}
+
+// Days since the epoch for a proleptic Gregorian date, and back again (Howard Hinnant's date algorithms)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_timestamps() {
+        assert_eq!(parse("1970-01-01T00:00:00"), Some(0));
+        assert_eq!(parse("2024-02-29T12:34:56"), Some(1709210096));
+        assert_eq!(format(1709210096), "2024-02-29T12:34:56");
+        // Fractions and timezones are ignored
+        assert_eq!(parse("2024-02-29T12:34:56.789+02:00"), Some(1709210096));
+    }
+
+    #[test]
+    fn rejects_bad_timestamps() {
+        for timestamp in ["", "2024-02-29", "2024-02-29 12:34:56", "2024-13-01T00:00:00", "2024-00-01T00:00:00", "2024-01-32T00:00:00",
+            "2024-01-01T24:00:00", "2024-01-01T00:60:00", "2024-01-01Tab:00:00", "1969-12-31T23:59:59"]
+        {
+            assert_eq!(parse(timestamp), None, "{}", timestamp);
+        }
+    }
+
+    #[test]
+    fn rejects_years_that_would_overflow() {
+        assert_eq!(parse("9223372036854775807-01-01T00:00:00"), None);
+        assert_eq!(parse("-9223372036854775808-01-01T00:00:00"), None);
+        assert_eq!(parse("10000-01-01T00:00:00"), None);
+    }
+}
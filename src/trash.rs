@@ -0,0 +1,189 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use crate::filesystem::{dir_size, mount_points, remove_path, xdg_dir};
+use crate::timestamp::{self, SECONDS_PER_DAY};
+
+pub const DEFAULT_MIN_AGE_DAYS: u64 = 30;
+
+// A single trashed item, made up of files/<name> and info/<name>.trashinfo
+pub struct TrashEntry {
+    pub trash_dir: PathBuf,
+    pub name: String,
+    pub deleted_at: Option<u64>, // Seconds since the epoch
+    pub size: u64,
+}
+
+#[derive(Default, Clone)]
+pub struct TrashSummary {
+    pub trash_dirs: usize,
+    pub total_items: usize,
+    pub total_bytes: u64,
+    pub expired_items: usize,
+    pub expired_bytes: u64,
+}
+
+impl TrashEntry {
+    fn file_path(&self) -> PathBuf {
+        self.trash_dir.join("files").join(&self.name)
+    }
+
+    fn info_path(&self) -> PathBuf {
+        self.trash_dir.join("info").join(format!("{}.trashinfo", self.name))
+    }
+
+    // Entries without a readable deletion date are never considered old enough, to stay on the safe side
+    pub fn is_older_than(&self, min_age_days: u64, now: u64) -> bool {
+        match self.deleted_at {
+            Some(deleted_at) => now.saturating_sub(deleted_at) >= min_age_days.saturating_mul(SECONDS_PER_DAY),
+            None => false,
+        }
+    }
+}
+
+// Finds the home trash and the per-mount trash directories of the current user, as laid out by the freedesktop.org trash spec
+pub fn find_trash_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home_trash) = xdg_dir("XDG_DATA_HOME", ".local/share").map(|dir| dir.join("Trash")) {
+        if home_trash.is_dir() {
+            dirs.push(home_trash);
+        }
+    }
+
+    let uid = unsafe { libc::getuid() };
+    for mount in mount_points() {
+        // $topdir/.Trash/$uid is only valid when .Trash is a sticky directory and not a symlink
+        let shared = mount.join(".Trash");
+        if let Ok(metadata) = fs::symlink_metadata(&shared) {
+            if metadata.is_dir() && metadata.mode() & 0o1000 != 0 {
+                let user_trash = shared.join(uid.to_string());
+                if user_trash.is_dir() {
+                    dirs.push(user_trash);
+                }
+            }
+        }
+
+        let user_trash = mount.join(format!(".Trash-{}", uid));
+        if fs::symlink_metadata(&user_trash).map(|m| m.is_dir()).unwrap_or(false) {
+            dirs.push(user_trash);
+        }
+    }
+
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+pub fn list_entries(trash_dir: &Path) -> Vec<TrashEntry> {
+    let mut entries = Vec::new();
+    let files = match fs::read_dir(trash_dir.join("files")) {
+        Ok(files) => files,
+        Err(_) => return entries,
+    };
+
+    for file in files.flatten() {
+        let name = file.file_name().to_string_lossy().into_owned();
+        let info = fs::read_to_string(trash_dir.join("info").join(format!("{}.trashinfo", name))).unwrap_or_default();
+        let deleted_at = parse_trashinfo(&info);
+        entries.push(TrashEntry {
+            trash_dir: trash_dir.to_path_buf(),
+            size: dir_size(&file.path()),
+            name,
+            deleted_at,
+        });
+    }
+    entries
+}
+
+pub fn summarize(min_age_days: u64) -> TrashSummary {
+    let now = timestamp::now();
+    let mut summary = TrashSummary::default();
+    for trash_dir in find_trash_dirs() {
+        summary.trash_dirs += 1;
+        for entry in list_entries(&trash_dir) {
+            summary.total_items += 1;
+            summary.total_bytes += entry.size;
+            if entry.is_older_than(min_age_days, now) {
+                summary.expired_items += 1;
+                summary.expired_bytes += entry.size;
+            }
+        }
+    }
+    summary
+}
+
+// Permanently deletes trashed items older than min_age_days, except those keep picks, returning the number of
+// items and bytes removed
+pub fn empty_older_than(min_age_days: u64, keep: impl Fn(&Path) -> bool) -> Result<(usize, u64), Vec<io::Error>> {
+    let now = timestamp::now();
+    let mut removed_items = 0;
+    let mut removed_bytes = 0;
+    let mut errors = Vec::new();
+
+    for trash_dir in find_trash_dirs() {
+        for entry in list_entries(&trash_dir) {
+            if !entry.is_older_than(min_age_days, now) || keep(&entry.file_path()) {
+                continue;
+            }
+            match remove_path(&entry.file_path()) {
+                Ok(()) => {
+                    // The info file goes last so a failed removal can still be restored by a file manager
+                    let _ = fs::remove_file(entry.info_path());
+                    removed_items += 1;
+                    removed_bytes += entry.size;
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok((removed_items, removed_bytes))
+    } else {
+        Err(errors)
+    }
+}
+
+// Returns the DeletionDate of a .trashinfo file. The date is in local time, but since ages are measured in days
+// the timezone offset is not worth accounting for, so it is treated as UTC.
+fn parse_trashinfo(contents: &str) -> Option<u64> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("DeletionDate="))
+        .and_then(|date| timestamp::parse(date.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deleted_at(deleted_at: Option<u64>) -> TrashEntry {
+        TrashEntry { trash_dir: PathBuf::from("/nonexistent"), name: String::from("file"), deleted_at, size: 0 }
+    }
+
+    #[test]
+    fn parses_the_deletion_date() {
+        let info = "[Trash Info]\nPath=/home/user/file\nDeletionDate=2024-02-29T12:34:56\n";
+        assert_eq!(parse_trashinfo(info), Some(1709210096));
+    }
+
+    #[test]
+    fn rejects_missing_or_bad_deletion_dates() {
+        assert_eq!(parse_trashinfo("[Trash Info]\nPath=/home/user/file\n"), None);
+        assert_eq!(parse_trashinfo("[Trash Info]\nDeletionDate=yesterday\n"), None);
+        assert_eq!(parse_trashinfo("[Trash Info]\nDeletionDate=99999999999999999999-01-01T00:00:00\n"), None);
+    }
+
+    #[test]
+    fn compares_ages_without_overflowing() {
+        let now = 100 * SECONDS_PER_DAY;
+        assert!(deleted_at(Some(0)).is_older_than(100, now));
+        assert!(!deleted_at(Some(0)).is_older_than(101, now));
+        assert!(!deleted_at(Some(0)).is_older_than(u64::MAX, now));
+        // Deleted in the future, eg. after the clock was turned back
+        assert!(!deleted_at(Some(now + 1)).is_older_than(1, now));
+        assert!(deleted_at(Some(now + 1)).is_older_than(0, now));
+        assert!(!deleted_at(None).is_older_than(0, now));
+    }
+}